@@ -0,0 +1,271 @@
+use std::{io::Write as _, path::Path};
+
+use anyhow::Context;
+use dms10_config::{
+    changes,
+    parser::Capture,
+    script::{Item, Script, Step},
+};
+use log::info;
+
+use crate::{
+    console::Console,
+    fetcher::{Fetcher, RetryPolicy},
+};
+
+/// Text that the DMS-10 prints when it rejects an answer.  It usually re-prompts for the same
+/// thing afterwards, so a rejected answer may instead show up as never reaching the expected
+/// prompt; either way the script stops.
+const ERRORS: &[&str] = &["ERROR", "ERR ", "INVALID", "NOT ALLOWED", "DENIED"];
+
+/// How far a script got.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Finished,
+    /// The user chose to stop before running this step (counted from 1).
+    Stopped(usize),
+}
+
+/// Run every step in `script`, asking before each one unless `yes` is set.  Comments are printed
+/// as they are reached so that it's clear which entity each step is part of.  Any sign of the
+/// DMS-10 rejecting an answer is an error.
+pub async fn run(console: &mut Console, script: &Script, yes: bool) -> anyhow::Result<Outcome> {
+    let total = script.steps().count();
+    let mut ask = !yes;
+    let mut number = 0;
+
+    for item in &script.items {
+        let step = match item {
+            Item::Comment(comment) => {
+                info!("# {}", comment);
+                continue;
+            }
+            Item::Step(step) => step,
+        };
+        number += 1;
+
+        if ask {
+            match confirm(number, total, step)? {
+                Answer::Yes => {}
+                Answer::All => ask = false,
+                Answer::Quit => return Ok(Outcome::Stopped(number)),
+            }
+        }
+
+        run_step(console, step)
+            .await
+            .with_context(|| format!("step {} of {}: {}", number, total, step))?;
+    }
+
+    Ok(Outcome::Finished)
+}
+
+/// Fetch every one of `fetchers` fresh and keep a copy of each capture in `dir`, under the same
+/// filename, along with a copy of `script`.  `dir` must not exist yet, so that an earlier backup is
+/// never overwritten.  Any capture that can't be fetched is an error, since the change can't be
+/// undone without it.
+pub async fn backup(
+    console: &mut Console,
+    fetchers: &[Fetcher],
+    policy: RetryPolicy,
+    dir: &Path,
+    script: &Path,
+) -> anyhow::Result<Vec<Capture>> {
+    if dir.exists() {
+        anyhow::bail!(
+            "{} already exists; move it out of the way so that it isn't overwritten",
+            dir.display()
+        );
+    }
+
+    let mut captures = vec![];
+    for fetcher in fetchers {
+        captures.push(
+            fetcher
+                .fetch_and_write_with_retries(console, policy)
+                .await?,
+        );
+
+        let backup = dir.join(fetcher.filename());
+        if let Some(parent) = backup.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating {}", parent.display()))?;
+        }
+        std::fs::copy(fetcher.filename(), &backup)
+            .with_context(|| format!("copying {} to {}", fetcher.filename(), backup.display()))?;
+    }
+
+    let name = script.file_name().context("the script has no file name")?;
+    std::fs::copy(script, dir.join(name))
+        .with_context(|| format!("copying {} to {}", script.display(), dir.display()))?;
+
+    info!(
+        "backed up {} capture(s) to {}",
+        captures.len(),
+        dir.display()
+    );
+    Ok(captures)
+}
+
+/// The script that takes every capture from `after` a change back to how it was `before`: entities
+/// that were added are removed, changed prompts get their old values back, and removed entities
+/// are added again.  Overlays are undone in the reverse of the order they are given in, so that
+/// whatever was added last (and might depend on something added before it) goes first.
+///
+/// A capture that couldn't be fetched after the change is left out, with a comment on how to roll
+/// it back from its copy in `backup` once it can be fetched again.
+pub fn rollback(captures: &[(&str, &Capture, Option<&Capture>)], backup: &Path) -> Script {
+    let mut script = Script::default();
+    for (filename, before, after) in captures.iter().rev() {
+        match after {
+            Some(after) => script.extend(changes::script(filename, after, before)),
+            None => {
+                script.comment(format!(
+                    "{} could not be fetched after the change, so it is not rolled back here.",
+                    filename
+                ));
+                script.comment(format!(
+                    "Fetch it again, then `changes {} {}` writes what rolls it back.",
+                    filename,
+                    backup.join(filename).display()
+                ));
+            }
+        }
+    }
+    if script.steps().next().is_none() {
+        script.comment("nothing was changed, so there is nothing to roll back");
+    }
+    script
+}
+
+async fn run_step(console: &mut Console, step: &Step) -> anyhow::Result<()> {
+    console.send(step.send.as_bytes()).await?;
+    let output = console.run_until_named_prompt(&step.expect).await?;
+
+    // the buffer starts with the echo of what was just sent, which isn't the DMS-10's reply
+    let reply = String::from_utf8_lossy(output.get(step.send.len()..).unwrap_or_default());
+    if let Some(error) = ERRORS.iter().find(|e| reply.contains(*e)) {
+        anyhow::bail!(
+            "the DMS-10 replied with {}: {}",
+            error.trim(),
+            reply.trim().escape_default()
+        );
+    }
+    Ok(())
+}
+
+enum Answer {
+    Yes,
+    All,
+    Quit,
+}
+
+fn confirm(number: usize, total: usize, step: &Step) -> anyhow::Result<Answer> {
+    let stdin = std::io::stdin();
+    loop {
+        eprint!(
+            "step {} of {}: {}\n  run it? [y]es, [a]ll remaining steps, [q]uit: ",
+            number, total, step
+        );
+        std::io::stderr().flush().context("flushing stderr")?;
+
+        let mut buf = String::new();
+        if stdin
+            .read_line(&mut buf)
+            .context("reading from stdin failed")?
+            == 0
+        {
+            return Ok(Answer::Quit);
+        }
+        match buf.trim() {
+            "y" => return Ok(Answer::Yes),
+            "a" => return Ok(Answer::All),
+            "q" => return Ok(Answer::Quit),
+            _ => eprintln!("That was not one of the options, try again."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rollback_undoes_the_change() {
+        let before = Capture::parse("    DN    5551234\n    LEN   0 2 03 05\n    PRFX  1\n");
+        let after = Capture::parse(
+            "    DN    5551234\n    LEN   0 2 03 05\n    PRFX  2\n\n    DN    5556634\n    LEN   0 2 03 07\n    PRFX  2\n",
+        );
+
+        let script = rollback(
+            &[("DN/DN.txt", &before, Some(&after))],
+            Path::new("change.backup"),
+        );
+        let sends: Vec<&str> = script.steps().map(|s| s.send.as_str()).collect();
+        assert_eq!(
+            sends,
+            [
+                "****\n",
+                "ovly dn\n",
+                // remove what was added
+                "out\n",
+                "dn\n",
+                "5556634\n",
+                // and put back what was changed
+                "chg\n",
+                "dn\n",
+                "5551234\n",
+                "\n",
+                "1\n",
+                "****\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn rollback_picks_out_translations_by_their_digits() {
+        let before = Capture::parse(
+            "    ADDR  1\n    DIGS  411\n    ROUT  1\n\n    ADDR  1\n    DIGS  611\n    ROUT  2\n",
+        );
+        let after = Capture::parse(
+            "    ADDR  1\n    DIGS  411\n    ROUT  1\n\n    ADDR  1\n    DIGS  611\n    ROUT  3\n\n    ADDR  1\n    DIGS  0\n    ROUT  5\n",
+        );
+
+        let script = rollback(
+            &[("TRNS/inactive/ADDR.txt", &before, Some(&after))],
+            Path::new("change.backup"),
+        );
+        let sends: Vec<&str> = script.steps().map(|s| s.send.as_str()).collect();
+        assert_eq!(
+            sends,
+            [
+                "****\n",
+                "ovly trns\n",
+                // remove what was added, leaving the translator's other entries
+                "out\n",
+                "addr\n",
+                "1\n",
+                "0\n",
+                // and put back the route of the one that was changed
+                "chg\n",
+                "addr\n",
+                "1\n",
+                "611\n",
+                "2\n",
+                "****\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn rollback_says_how_to_finish_without_the_result() {
+        let before = Capture::parse("    DN    5551234\n    PRFX  1\n");
+
+        let script = rollback(&[("DN/DN.txt", &before, None)], Path::new("change.backup"));
+        assert_eq!(script.steps().count(), 0);
+        assert!(script.items.iter().any(|item| matches!(
+            item,
+            Item::Comment(c) if c.contains("`changes DN/DN.txt change.backup/DN/DN.txt`")
+        )));
+    }
+}
@@ -0,0 +1,271 @@
+//! Scripts of DMO `OUT`, `CHG` and `NEW` requests that would take one table from how it was
+//! captured to how it should be, e.g. after editing an export of `DN/DN.txt`.
+//!
+//! Entities are matched up the same way `diff` does it, by their key, which for a TRNS entry is its
+//! translator and digits; `OUT` and `CHG` answer both to pick it out.  Removals come first so that
+//! whatever they were using (a line circuit, a number) is free for anything added in its place;
+//! then changes, then additions.  A `CHG` is asked every prompt in turn, so it answers each one
+//! that didn't change with an empty line to keep its value.  The prompts are asked in the order
+//! `QUE` prints them across every record of the table (a prompt that `QUE` never printed for any
+//! record can't be known), which is worth checking when reviewing the script.
+
+use crate::{
+    diff::CaptureDiff,
+    parser::{Capture, Record},
+    rebuild,
+    script::Script,
+    translations::{self, Entry, Table},
+};
+
+/// The overlay that a capture (e.g. `TRNS/active/ADDR.txt`) is fetched from, e.g. `trns`.
+pub fn overlay(filename: &str) -> String {
+    filename
+        .split('/')
+        .next()
+        .unwrap_or(filename)
+        .to_lowercase()
+}
+
+/// The requests that turn `current` (the capture at `filename`) into `desired`.  The script is
+/// empty if they are the same.
+pub fn script(filename: &str, current: &Capture, desired: &Capture) -> Script {
+    let diff = CaptureDiff::for_file(filename, current, desired);
+    let mut script = Script::default();
+    if diff.is_empty() {
+        return script;
+    }
+
+    let typ = rebuild::typ(filename);
+    let trns = Table::from_capture(filename).is_some();
+    let prompts = prompt_order(current, desired);
+    // a desired state written by hand has no line numbers, so keep it in the order it was written
+    let position = |record: &Record| desired.records.iter().position(|r| r == record);
+    let mut added = diff.added.clone();
+    added.sort_by_key(|r| position(r));
+    let mut modified = diff.modified.clone();
+    modified.sort_by_key(|m| position(&m.record));
+
+    script.comment(format!(
+        "{}: {} to remove, {} to change, {} to add",
+        filename,
+        diff.removed.len(),
+        modified.len(),
+        added.len()
+    ));
+    script.enter_overlay(&overlay(filename));
+
+    for record in &diff.removed {
+        let Some(selection) = selection(filename, record) else {
+            continue;
+        };
+        script.comment(format!(
+            "remove {}  ({}:{})",
+            describe(&selection),
+            filename,
+            record.line()
+        ));
+        script.request("out", &typ, &fields(&selection));
+    }
+
+    for modified in &modified {
+        let Some(selection) = selection(filename, &modified.record) else {
+            continue;
+        };
+        script.comment(format!("change {}", modified.key));
+        let mut fields = fields(&selection);
+        // the digits are only ever answered as part of picking out a TRNS entry
+        let is_digits = |p: &str| trns && translations::DIGITS.contains(&p);
+        for &other in prompts
+            .iter()
+            .filter(|&&p| !is_digits(p) && selection.iter().all(|(prompt, _)| *prompt != p))
+        {
+            let change = modified.changes.iter().find(|c| c.prompt == other);
+            match change {
+                Some(change) if !change.new.is_empty() => fields.push((other, &change.new)),
+                Some(change) => {
+                    script.comment(format!(
+                        "{} is no longer wanted; it is left as it was, so clear it by hand if the DMS-10 keeps it",
+                        change.prompt
+                    ));
+                    fields.push((other, &[]));
+                }
+                None => fields.push((other, &[])),
+            }
+        }
+        script.request("chg", &typ, &fields);
+    }
+
+    for record in &added {
+        let (prompt, value) = record.key().unwrap_or_default();
+        script.comment(format!("add {}  {}", prompt, value));
+        script.request_record("new", &typ, record);
+    }
+
+    script.leave_overlay();
+    script
+}
+
+/// The prompts and answers that pick out `record` for `OUT` and `CHG`: its key, or for a TRNS
+/// table its translator and then its digits (see [`Entry::selection`]), since a translator has
+/// many entries.
+fn selection<'a>(filename: &str, record: &'a Record) -> Option<Vec<(&'a str, Vec<String>)>> {
+    let selection = match Table::from_capture(filename) {
+        Some(table) => Entry::selection(table, record)?,
+        None => {
+            let (prompt, value) = record.key()?;
+            vec![(prompt, value.to_owned())]
+        }
+    };
+    Some(
+        selection
+            .into_iter()
+            .map(|(prompt, value)| (prompt, vec![value]))
+            .collect(),
+    )
+}
+
+fn fields<'a>(selection: &'a [(&'a str, Vec<String>)]) -> Vec<(&'a str, &'a [String])> {
+    selection
+        .iter()
+        .map(|(prompt, values)| (*prompt, values.as_slice()))
+        .collect()
+}
+
+/// e.g. `ADDR  1  DIGS  611`, for a comment.
+fn describe(selection: &[(&str, Vec<String>)]) -> String {
+    let parts: Vec<String> = selection
+        .iter()
+        .map(|(prompt, values)| format!("{}  {}", prompt, values.join(" ")))
+        .collect();
+    parts.join("  ")
+}
+
+/// Every prompt of either capture, in the order the DMS-10 prints them; see `Capture::prompts`.
+fn prompt_order<'a>(current: &'a Capture, desired: &'a Capture) -> Vec<&'a str> {
+    let mut prompts = current.prompts();
+    let mut previous: Option<usize> = None;
+    for prompt in desired.prompts() {
+        let index = match prompts.iter().position(|&p| p == prompt) {
+            Some(index) => index,
+            None => {
+                let index = previous.map_or(prompts.len(), |p| p + 1);
+                prompts.insert(index, prompt);
+                index
+            }
+        };
+        previous = Some(index);
+    }
+    prompts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::Item;
+
+    fn steps(script: &Script) -> Vec<(&str, &str)> {
+        script
+            .steps()
+            .map(|s| (s.send.as_str(), s.expect.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn chg_answers_every_prompt_in_order() {
+        let current = Capture::parse(
+            "    DN    5551234\n    LEN   0 2 03 05\n    PRFX  1\n\n    DN    5556634\n    LEN   0 2 03 07\n    HTGP  4\n    PRFX  1\n",
+        );
+        let desired = Capture::parse(
+            "    DN    5551234\n    LEN   0 2 03 05\n    PRFX  2\n\n    DN    5556634\n    LEN   0 2 03 07\n    HTGP  4\n    PRFX  1\n",
+        );
+
+        let script = script("DN/DN.txt", &current, &desired);
+        assert_eq!(
+            steps(&script),
+            [
+                ("****\n", "#"),
+                ("ovly dn\n", "REQ"),
+                ("chg\n", "TYP"),
+                ("dn\n", "DN"),
+                ("5551234\n", "LEN"),
+                ("\n", "HTGP"),
+                ("\n", "PRFX"),
+                ("2\n", "REQ"),
+                ("****\n", "#"),
+            ]
+        );
+    }
+
+    #[test]
+    fn trns_entries_are_picked_out_by_translator_and_digits() {
+        let current = Capture::parse(
+            "    ADDR  1\n    DIGS  411\n    ROUT  1\n\n    ADDR  1\n    DIGS  611\n    ROUT  2\n",
+        );
+        let desired = Capture::parse("    ADDR  1\n    DIGS  611\n    ROUT  3\n");
+
+        let script = script("TRNS/inactive/ADDR.txt", &current, &desired);
+        assert_eq!(
+            steps(&script),
+            [
+                ("****\n", "#"),
+                ("ovly trns\n", "REQ"),
+                ("out\n", "TYP"),
+                ("addr\n", "ADDR"),
+                ("1\n", "DIGS"),
+                ("411\n", "REQ"),
+                ("chg\n", "TYP"),
+                ("addr\n", "ADDR"),
+                ("1\n", "DIGS"),
+                ("611\n", "ROUT"),
+                ("3\n", "REQ"),
+                ("****\n", "#"),
+            ]
+        );
+    }
+
+    #[test]
+    fn trns_entry_without_digits_never_answers_the_digits_prompt() {
+        let current =
+            Capture::parse("    PRFX  2\n    ROUT  1\n\n    PRFX  3\n    DIGS  6\n    ROUT  4\n");
+        let desired =
+            Capture::parse("    PRFX  2\n    ROUT  5\n\n    PRFX  3\n    DIGS  6\n    ROUT  4\n");
+
+        let script = script("TRNS/inactive/PRFX.txt", &current, &desired);
+        assert_eq!(
+            steps(&script)[2..6],
+            [
+                ("chg\n", "TYP"),
+                ("prfx\n", "PRFX"),
+                ("2\n", "ROUT"),
+                ("5\n", "REQ"),
+            ]
+        );
+    }
+
+    #[test]
+    fn removed_prompt_is_kept_with_a_comment() {
+        let current = Capture::parse("    DN    5551234\n    LEN   0 2 03 05\n    PRFX  1\n");
+        let desired = Capture::parse("    DN    5551234\n    LEN   0 2 03 06\n");
+
+        let script = script("DN/DN.txt", &current, &desired);
+        assert!(script.items.iter().any(
+            |item| matches!(item, Item::Comment(c) if c.starts_with("PRFX is no longer wanted"))
+        ));
+        let steps = steps(&script);
+        assert_eq!(
+            steps[3..7],
+            [
+                ("dn\n", "DN"),
+                ("5551234\n", "LEN"),
+                ("0 2 03 06\n", "PRFX"),
+                ("\n", "REQ"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unchanged_is_empty() {
+        let capture = Capture::parse("    DN    5551234\n    PRFX  1\n");
+        assert!(script("DN/DN.txt", &capture, &capture).items.is_empty());
+    }
+}
@@ -1,7 +1,11 @@
-use std::{cmp::min, process::Stdio, time::Duration};
+use std::{
+    cmp::min,
+    process::Stdio,
+    time::{Duration, Instant},
+};
 
 use anyhow::Context;
-use log::{debug, warn};
+use log::{debug, error, warn};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     process::{ChildStdin, ChildStdout, Command},
@@ -13,10 +17,46 @@ const TIMEOUT: Duration = Duration::from_secs(5);
 // number of bytes to include in these warnings
 const LOOKBACK: usize = 100;
 
+// what may be answered at a DMO's REQ prompt in read-only mode
+const READ_REQUESTS: &[&str] = &["que", "quei"];
+
+/// The telnet process went away, so nothing more can be sent or received on this console.
+#[derive(Debug)]
+pub struct Disconnected;
+
+impl std::fmt::Display for Disconnected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the connection to the DMS-10 was lost")
+    }
+}
+
+impl std::error::Error for Disconnected {}
+
+/// Whether `e` (or anything that caused it) is a `Disconnected`.
+pub fn is_disconnected(e: &anyhow::Error) -> bool {
+    e.chain().any(|cause| cause.is::<Disconnected>())
+}
+
+/// Whether `send` may change the DMS-10's configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    /// Only `que`/`quei` may be sent at a `REQ` prompt, and only `ovly` at the `  # ` prompt.
+    /// Everything is sent one line at a time, and only once the DMS-10 has asked for it.  `****` to
+    /// back out is always allowed.
+    ReadOnly,
+    ReadWrite,
+}
+
 pub struct Console {
     stdin: ChildStdin,
     stdout: ChildStdout,
     buffer: Vec<u8>,
+    give_up_after: Option<Duration>,
+    access: Access,
+    // the prompt that was most recently waited for, i.e. what the next `send` is answering
+    prompt: Option<String>,
+    // whether anything has been sent since that prompt
+    answered: bool,
 }
 
 impl Console {
@@ -40,25 +80,68 @@ impl Console {
             stdin,
             stdout,
             buffer: vec![],
+            give_up_after: None,
+            access: Access::ReadOnly,
+            prompt: None,
+            answered: false,
         })
     }
 
+    /// Turn the "stuck" warnings in `run_until_human_prompt` into an error once a prompt has not
+    /// been seen for this long.  `None` (the default) keeps waiting forever.
+    pub fn set_give_up_after(&mut self, give_up_after: Option<Duration>) {
+        self.give_up_after = give_up_after;
+    }
+
+    /// Allow or forbid requests that change the configuration.  Consoles start out read-only.
+    pub fn set_access(&mut self, access: Access) {
+        self.access = access;
+    }
+
     pub async fn run_until_human_prompt(
         &mut self,
         expected_prompt: &str,
     ) -> anyhow::Result<Vec<u8>> {
+        self.run_until(Prompt::Exact(expected_prompt)).await
+    }
+
+    /// Like `run_until_human_prompt`, but for a prompt given by name (e.g. `TYP` or `#`) regardless
+    /// of how the DMS-10 pads it, which differs between overlays.
+    pub async fn run_until_named_prompt(&mut self, name: &str) -> anyhow::Result<Vec<u8>> {
+        self.run_until(Prompt::Named(name)).await
+    }
+
+    async fn run_until(&mut self, prompt: Prompt<'_>) -> anyhow::Result<Vec<u8>> {
+        let expected_prompt = match prompt {
+            Prompt::Exact(expected) | Prompt::Named(expected) => expected,
+        };
+        let start = Instant::now();
         loop {
-            match tokio::time::timeout(TIMEOUT, self.read_until_prompt(expected_prompt)).await {
+            match tokio::time::timeout(TIMEOUT, self.read_until_prompt(prompt)).await {
                 Err(_elapsed) => {
                     warn!(
                         "DMS-10 has not reached the expected prompt \"{}\", tail of the buffer is: \"{}\"",
                         expected_prompt.as_bytes().escape_ascii(),
                         self.buffer[(self.buffer.len() - (min(self.buffer.len(), LOOKBACK)))..]
                             .escape_ascii()
-                    )
+                    );
+
+                    if let Some(give_up_after) = self.give_up_after {
+                        if start.elapsed() >= give_up_after {
+                            anyhow::bail!(
+                                "gave up waiting {:?} for \"{}\"",
+                                give_up_after,
+                                expected_prompt.as_bytes().escape_ascii()
+                            );
+                        }
+                    }
                 }
                 Ok(result) => match result {
-                    Ok(()) => return Ok(std::mem::take(&mut self.buffer)),
+                    Ok(()) => {
+                        self.prompt = Some(expected_prompt.to_owned());
+                        self.answered = false;
+                        return Ok(std::mem::take(&mut self.buffer));
+                    }
                     Err(e) => return Err(e).context("reading from socket"),
                 },
             }
@@ -66,11 +149,18 @@ impl Console {
     }
 
     pub async fn send(&mut self, data: &[u8]) -> anyhow::Result<()> {
+        if self.access == Access::ReadOnly {
+            check_read_only(self.prompt.as_deref(), self.answered, data)
+                .inspect_err(|e| error!("refusing to send \"{}\": {:#}", data.escape_ascii(), e))?;
+        }
+        self.answered = true;
         debug!("sending: {}", data.escape_ascii());
-        self.stdin
-            .write_all(data)
-            .await
-            .context("writing to child")?;
+        match self.stdin.write_all(data).await {
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {
+                return Err(anyhow::Error::new(e).context(Disconnected))
+            }
+            result => result.context("writing to child")?,
+        }
 
         // pretend we're echoing all typed words to the screen, so pre-load the buffer with the data
         // we just sent.
@@ -79,11 +169,15 @@ impl Console {
         Ok(())
     }
 
-    async fn read_until_prompt(&mut self, expected_prompt: &str) -> anyhow::Result<()> {
+    async fn read_until_prompt(&mut self, prompt: Prompt<'_>) -> anyhow::Result<()> {
         loop {
             self.read_into_buffer().await?;
 
-            if self.check_buffer_tail(expected_prompt) {
+            let found = match prompt {
+                Prompt::Exact(expected) => self.check_buffer_tail(expected),
+                Prompt::Named(name) => ends_with_named_prompt(&self.buffer, name),
+            };
+            if found {
                 return Ok(());
             }
         }
@@ -96,7 +190,7 @@ impl Console {
         let count = self.stdout.read_buf(&mut new_buf).await?;
         if count == 0 {
             debug!("EOF!");
-            anyhow::bail!("subprocess returned EOF");
+            return Err(Disconnected.into());
         }
         debug!("received: \"{}\"", new_buf.escape_ascii());
         self.buffer.append(&mut new_buf);
@@ -116,3 +210,123 @@ impl Console {
         false
     }
 }
+
+/// What a read-only console may send, given the prompt it is answering (`None` before the first
+/// one, while logging in) and whether it has already answered it.  Every prompt other than `REQ` and
+/// `  # ` is either part of logging in or follows a `REQ` that has already been checked.
+fn check_read_only(prompt: Option<&str>, answered: bool, data: &[u8]) -> anyhow::Result<()> {
+    let text = String::from_utf8_lossy(data);
+    let answer = text.trim().to_lowercase();
+    if answer == "****" {
+        return Ok(());
+    }
+    // otherwise a second line would be answering whatever prompt the first one leads to
+    if text.trim_end_matches(['\r', '\n']).contains(['\r', '\n']) {
+        anyhow::bail!("the console is read-only, so only one line can be sent at a time");
+    }
+    let Some(prompt) = prompt else {
+        return Ok(());
+    };
+    if answered {
+        anyhow::bail!(
+            "the console is read-only, and the {} prompt has already been answered",
+            prompt.trim()
+        );
+    }
+
+    let allowed = match prompt.trim() {
+        "REQ" => READ_REQUESTS.contains(&answer.as_str()),
+        "#" => answer.is_empty() || answer.starts_with("ovly "),
+        _ => true,
+    };
+    if !allowed {
+        anyhow::bail!(
+            "the console is read-only, so only {} and ovly can be sent at the {} prompt",
+            READ_REQUESTS.join("/"),
+            prompt.trim()
+        );
+    }
+    Ok(())
+}
+
+// The last line is the prompt if it's the name padded with spaces.  Every DMS-10 prompt ends in a
+// space, so this doesn't match a line that is still being printed.
+fn ends_with_named_prompt(buffer: &[u8], name: &str) -> bool {
+    let Some(start) = buffer.iter().rposition(|&b| b == b'\n' || b == b'\r') else {
+        return false;
+    };
+    let line = &buffer[start + 1..];
+    line.ends_with(b" ") && line.trim_ascii() == name.trim().as_bytes()
+}
+
+#[derive(Clone, Copy)]
+enum Prompt<'a> {
+    /// Exactly these bytes, padding and all.
+    Exact(&'a str),
+    /// The name of the prompt, however it is padded.
+    Named(&'a str),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQ: Option<&str> = Some("    REQ   ");
+    const TYP: Option<&str> = Some("    TYP   ");
+    const HASH: Option<&str> = Some("#");
+
+    #[test]
+    fn read_only_allows_queries_at_req() {
+        assert!(check_read_only(REQ, false, b"que\n").is_ok());
+        assert!(check_read_only(REQ, false, b"QUEI\r\n").is_ok());
+        for request in ["new\n", "chg\n", "out\n", "\n"] {
+            assert!(check_read_only(REQ, false, request.as_bytes()).is_err());
+        }
+    }
+
+    #[test]
+    fn read_only_allows_only_ovly_at_hash() {
+        assert!(check_read_only(HASH, false, b"ovly dn\n").is_ok());
+        assert!(check_read_only(HASH, false, b"\n").is_ok());
+        assert!(check_read_only(HASH, false, b"logo\n").is_err());
+    }
+
+    #[test]
+    fn read_only_always_allows_backing_out() {
+        for prompt in [None, REQ, TYP, HASH] {
+            assert!(check_read_only(prompt, false, b"****\n").is_ok());
+            assert!(check_read_only(prompt, true, b"****\n").is_ok());
+        }
+    }
+
+    #[test]
+    fn read_only_refuses_more_than_one_line() {
+        assert!(check_read_only(HASH, false, b"ovly dn\nnew\n").is_err());
+        assert!(check_read_only(TYP, false, b"dn\n****\novly dn\nnew\n").is_err());
+        assert!(check_read_only(None, false, b"root\nnew\n").is_err());
+    }
+
+    #[test]
+    fn read_only_refuses_typing_ahead() {
+        assert!(check_read_only(TYP, false, b"dn\n").is_ok());
+        assert!(check_read_only(TYP, true, b"new\n").is_err());
+    }
+
+    #[test]
+    fn named_prompt_ignores_padding() {
+        assert!(ends_with_named_prompt(b"que\r\n    TYP   ", "TYP"));
+        assert!(ends_with_named_prompt(b"que\r\n    TYP    ", "TYP"));
+        assert!(ends_with_named_prompt(b"tg\r\n    NUM    ", "NUM"));
+        assert!(ends_with_named_prompt(b"****\r\n  # ", "#"));
+        assert!(ends_with_named_prompt(b"ABC\r\n          ", ""));
+    }
+
+    #[test]
+    fn named_prompt_must_be_the_whole_last_line() {
+        // still being printed
+        assert!(!ends_with_named_prompt(b"que\r\n    TYP", "TYP"));
+        assert!(!ends_with_named_prompt(b"    TYP   ", "TYP"));
+        assert!(!ends_with_named_prompt(b"\r\n    TYPE  ", "TYP"));
+        assert!(!ends_with_named_prompt(b"\r\n    DN    555 ", "DN"));
+    }
+}
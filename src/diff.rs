@@ -0,0 +1,387 @@
+//! Record-level comparison of two captures of the same table.
+//!
+//! Records are matched up by their key (the first prompt and its value, e.g. `DN  5551234`) rather
+//! than by position, so a capture where the DMS-10 printed the same entities in a different order
+//! compares as unchanged.  Translations are the exception: every entry of a translator can share
+//! the same first prompt, so they are matched by table, translator and digits instead.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::{
+    parser::{Capture, Record},
+    translations::{Entry, Table},
+};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldChange {
+    pub prompt: String,
+    /// Empty if the prompt was added.
+    pub old: Vec<String>,
+    /// Empty if the prompt was removed.
+    pub new: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Modified {
+    pub key: String,
+    pub old_line: usize,
+    pub new_line: usize,
+    pub changes: Vec<FieldChange>,
+    /// The record as it is now.
+    #[serde(skip)]
+    pub record: Record,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CaptureDiff {
+    pub added: Vec<Record>,
+    pub removed: Vec<Record>,
+    pub modified: Vec<Modified>,
+    /// The translation table that was compared, whose records are keyed differently.
+    #[serde(skip)]
+    table: Option<Table>,
+}
+
+impl CaptureDiff {
+    /// Compare two captures, matching records by their first prompt and its value.
+    pub fn new(old: &Capture, new: &Capture) -> Self {
+        Self::keyed_by(old, new, None)
+    }
+
+    /// Compare two captures of `filename`, matching records the way that capture needs: by their
+    /// entry's key for translations, and by their first prompt otherwise.
+    pub fn for_file(filename: &str, old: &Capture, new: &Capture) -> Self {
+        Self::keyed_by(old, new, Table::from_capture(filename))
+    }
+
+    fn keyed_by(old: &Capture, new: &Capture, table: Option<Table>) -> Self {
+        let mut diff = CaptureDiff {
+            table,
+            ..Default::default()
+        };
+        let old_records = diff.keyed(old);
+        let mut new_records = diff.keyed(new);
+
+        for (key, old_record) in old_records {
+            match new_records.remove(&key) {
+                None => diff.removed.push(old_record.clone()),
+                Some(new_record) => {
+                    let changes = compare(old_record, new_record);
+                    if !changes.is_empty() {
+                        diff.modified.push(Modified {
+                            key: display_key(&key),
+                            old_line: old_record.line(),
+                            new_line: new_record.line(),
+                            changes,
+                            record: new_record.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        diff.added = new_records.into_values().cloned().collect();
+
+        diff.added.sort_by_key(Record::line);
+        diff.removed.sort_by_key(Record::line);
+        diff.modified.sort_by_key(|m| m.new_line);
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    fn key(&self, record: &Record) -> String {
+        self.table
+            .and_then(|table| Entry::key_of(table, record))
+            .unwrap_or_else(|| record_key(record))
+    }
+
+    fn keyed<'a>(&self, capture: &'a Capture) -> HashMap<Key, &'a Record> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut records = HashMap::new();
+        for record in &capture.records {
+            let key = self.key(record);
+            let count = counts.entry(key.clone()).or_default();
+            records.insert((key, *count), record);
+            *count += 1;
+        }
+        records
+    }
+}
+
+/// A summary in the style of a unified diff: `+` for added records, `-` for removed ones and `~`
+/// for ones whose prompts changed.
+impl fmt::Display for CaptureDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for record in &self.removed {
+            writeln!(f, "- {}  (old line {})", self.key(record), record.line())?;
+        }
+        for record in &self.added {
+            writeln!(f, "+ {}  (new line {})", self.key(record), record.line())?;
+        }
+        for modified in &self.modified {
+            writeln!(
+                f,
+                "~ {}  (old line {}, new line {})",
+                modified.key, modified.old_line, modified.new_line
+            )?;
+            for change in &modified.changes {
+                writeln!(
+                    f,
+                    "      {:4}  {} -> {}",
+                    change.prompt,
+                    values_or_none(&change.old),
+                    values_or_none(&change.new)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Somewhere a whole set of captures can be read from: a directory that `fetch` was run in, or a
+/// commit in a git repository that such a directory was committed to.
+#[derive(Clone, Debug)]
+pub enum CaptureSet {
+    Dir(PathBuf),
+    Git { repo: PathBuf, rev: String },
+}
+
+impl CaptureSet {
+    /// Interpret `spec` as a directory if one exists by that name, or otherwise as a revision in the
+    /// git repository at `repo`.
+    pub fn new(spec: &str, repo: impl Into<PathBuf>) -> Self {
+        if Path::new(spec).is_dir() {
+            CaptureSet::Dir(spec.into())
+        } else {
+            CaptureSet::Git {
+                repo: repo.into(),
+                rev: spec.to_owned(),
+            }
+        }
+    }
+
+    /// The relative path of every capture in the set, e.g. `CPK/PACK.txt`.
+    pub fn list(&self) -> anyhow::Result<BTreeSet<String>> {
+        match self {
+            CaptureSet::Dir(dir) => {
+                let mut files = BTreeSet::new();
+                walk(dir, dir, &mut files)?;
+                Ok(files)
+            }
+            CaptureSet::Git { repo, rev } => {
+                let output = git(repo, &["ls-tree", "-r", "--name-only", rev])?;
+                Ok(output
+                    .lines()
+                    .filter(|l| l.ends_with(".txt"))
+                    .map(str::to_owned)
+                    .collect())
+            }
+        }
+    }
+
+    pub fn read(&self, filename: &str) -> anyhow::Result<Capture> {
+        match self {
+            CaptureSet::Dir(dir) => Capture::from_file(dir.join(filename)),
+            CaptureSet::Git { repo, rev } => Ok(Capture::parse(&git(
+                repo,
+                &["show", &format!("{}:{}", rev, filename)],
+            )?)),
+        }
+    }
+}
+
+impl fmt::Display for CaptureSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureSet::Dir(dir) => write!(f, "{}", dir.display()),
+            CaptureSet::Git { rev, .. } => write!(f, "{}", rev),
+        }
+    }
+}
+
+/// Every capture that is in either set, and how it differs.
+#[derive(Debug, Default, Serialize)]
+pub struct SetDiff {
+    pub only_old: Vec<String>,
+    pub only_new: Vec<String>,
+    pub changed: Vec<(String, CaptureDiff)>,
+}
+
+impl SetDiff {
+    pub fn new(old: &CaptureSet, new: &CaptureSet) -> anyhow::Result<Self> {
+        let old_files = old.list().with_context(|| format!("listing {}", old))?;
+        let new_files = new.list().with_context(|| format!("listing {}", new))?;
+
+        let mut diff = SetDiff {
+            only_old: old_files.difference(&new_files).cloned().collect(),
+            only_new: new_files.difference(&old_files).cloned().collect(),
+            changed: vec![],
+        };
+        for filename in old_files.intersection(&new_files) {
+            let old_capture = old
+                .read(filename)
+                .with_context(|| format!("reading {} from {}", filename, old))?;
+            let new_capture = new
+                .read(filename)
+                .with_context(|| format!("reading {} from {}", filename, new))?;
+            let capture_diff = CaptureDiff::for_file(filename, &old_capture, &new_capture);
+            if !capture_diff.is_empty() {
+                diff.changed.push((filename.clone(), capture_diff));
+            }
+        }
+        Ok(diff)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.only_old.is_empty() && self.only_new.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for SetDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for filename in &self.only_old {
+            writeln!(f, "{}: only in the old captures", filename)?;
+        }
+        for filename in &self.only_new {
+            writeln!(f, "{}: only in the new captures", filename)?;
+        }
+        for (filename, diff) in &self.changed {
+            writeln!(f, "{}:", filename)?;
+            write!(f, "{}", diff)?;
+        }
+        Ok(())
+    }
+}
+
+fn walk(root: &Path, dir: &Path, files: &mut BTreeSet<String>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+        let path = entry?.path();
+        if path
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+        {
+            continue;
+        }
+        if path.is_dir() {
+            walk(root, &path, files)?;
+        } else if path.extension().is_some_and(|e| e == "txt") {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            files.insert(relative.to_string_lossy().into_owned());
+        }
+    }
+    Ok(())
+}
+
+fn git(repo: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .context("running git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// (key, occurrence), so that a key printed twice doesn't hide the second record
+type Key = (String, usize);
+
+fn compare(old: &Record, new: &Record) -> Vec<FieldChange> {
+    let mut changes = vec![];
+    for field in old.fields() {
+        let new_values = new.get(&field.prompt).unwrap_or_default();
+        if !same_values(&field.values, new_values) {
+            changes.push(FieldChange {
+                prompt: field.prompt.clone(),
+                old: field.values.clone(),
+                new: new_values.to_vec(),
+            });
+        }
+    }
+    for field in new.fields() {
+        if old.get(&field.prompt).is_none() {
+            changes.push(FieldChange {
+                prompt: field.prompt.clone(),
+                old: vec![],
+                new: field.values.clone(),
+            });
+        }
+    }
+    changes
+}
+
+// the DMS-10 sometimes pads values differently between software loads, which is not a change
+fn same_values(old: &[String], new: &[String]) -> bool {
+    old.len() == new.len()
+        && old
+            .iter()
+            .zip(new)
+            .all(|(o, n)| normalize(o) == normalize(n))
+}
+
+fn normalize(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn display_key((key, occurrence): &Key) -> String {
+    if *occurrence == 0 {
+        key.clone()
+    } else {
+        format!("{}  (#{})", key, occurrence + 1)
+    }
+}
+
+fn record_key(record: &Record) -> String {
+    let (prompt, value) = record.key().unwrap_or_default();
+    format!("{}  {}", prompt, normalize(value))
+}
+
+fn values_or_none(values: &[String]) -> String {
+    if values.is_empty() {
+        "(none)".to_owned()
+    } else {
+        values.join(" / ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translations_are_matched_by_their_digits() {
+        let old = Capture::parse(
+            "    ADDR  1\n    DIGS  411\n    ROUT  1\n\n    ADDR  1\n    DIGS  611\n    ROUT  2\n",
+        );
+        let new = Capture::parse(
+            "    ADDR  1\n    DIGS  0\n    ROUT  5\n\n    ADDR  1\n    DIGS  411\n    ROUT  1\n\n    ADDR  1\n    DIGS  611\n    ROUT  3\n",
+        );
+
+        let diff = CaptureDiff::for_file("TRNS/inactive/ADDR.txt", &old, &new);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].first("DIGS"), Some("0"));
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].key, "ADDR 1 611");
+        assert_eq!(diff.modified[0].changes[0].prompt, "ROUT");
+
+        // matched by position, every entry after the inserted one looks changed
+        assert_eq!(CaptureDiff::new(&old, &new).modified.len(), 2);
+    }
+}
@@ -0,0 +1,225 @@
+//! Directory numbers, as captured from the `DN` overlay (`DN/DN.txt` and `DN/STN.txt`).
+
+use std::{fmt, path::Path};
+
+use serde::Serialize;
+
+use crate::{
+    inventory,
+    parser::{Capture, Record},
+};
+
+/// The captures that directory numbers are read from, in the order they are searched.
+pub const CAPTURES: &[&str] = &["DN/DN.txt", "DN/STN.txt"];
+
+// The prompts for each attribute, in order of preference where the DMS-10 has more than one
+// spelling for the same thing.
+const DN: &[&str] = &["DN"];
+const LEN: &[&str] = &["LEN", "LTID"];
+const CLASS: &[&str] = &["CLS", "COS"];
+const OPTIONS: &[&str] = &["OPTS", "OPT", "FEAT"];
+const HUNT: &[&str] = &["HTGP", "HUNT"];
+
+/// The fewest trailing digits that `DirectoryNumbers::find` will match a number on, i.e. a station
+/// number without its office code.
+const MIN_SUFFIX: usize = 4;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DirectoryNumber {
+    /// The number exactly as the DMS-10 printed it.
+    pub dn: String,
+    /// Line equipment number, i.e. where the line circuit is physically located.
+    pub len: Option<String>,
+    pub class: Option<String>,
+    pub options: Vec<String>,
+    /// Hunt groups that this number is a member of.
+    pub hunt_groups: Vec<String>,
+    /// The capture this number was read from, e.g. `DN/DN.txt`.
+    pub source: &'static str,
+    pub line: usize,
+    /// Everything else that was printed about this number.
+    pub record: Record,
+}
+
+impl DirectoryNumber {
+    fn from_record(record: &Record, source: &'static str) -> Option<Self> {
+        Some(Self {
+            dn: record.first_any(DN)?.to_owned(),
+            len: record.first_any(LEN).map(str::to_owned),
+            class: record.first_any(CLASS).map(str::to_owned),
+            options: record.get_any(OPTIONS).unwrap_or_default().to_vec(),
+            hunt_groups: record.get_any(HUNT).unwrap_or_default().to_vec(),
+            source,
+            line: record.line(),
+            record: record.clone(),
+        })
+    }
+
+    /// Just the digits of the number, so that `555-1234`, `555 1234` and `5551234` are the same.
+    pub fn digits(&self) -> String {
+        digits(&self.dn)
+    }
+}
+
+impl fmt::Display for DirectoryNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}  ({}:{})", self.dn, self.source, self.line)?;
+        writeln!(
+            f,
+            "    line equipment  {}",
+            self.len.as_deref().unwrap_or("(none)")
+        )?;
+        writeln!(
+            f,
+            "    class           {}",
+            self.class.as_deref().unwrap_or("(none)")
+        )?;
+        writeln!(f, "    options         {}", list_or_none(&self.options))?;
+        writeln!(f, "    hunt groups     {}", list_or_none(&self.hunt_groups))
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DirectoryNumbers {
+    numbers: Vec<DirectoryNumber>,
+}
+
+impl DirectoryNumbers {
+    /// Read every directory number from the captures in `dir`.  Captures that don't exist are
+    /// skipped.
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut numbers = Self::default();
+        for &source in CAPTURES {
+            if let Some(capture) = Capture::load(dir.as_ref(), source)? {
+                numbers.extend(&capture, source);
+            }
+        }
+        Ok(numbers)
+    }
+
+    /// Add the directory numbers in `capture`, which was read from `source`.
+    pub fn extend(&mut self, capture: &Capture, source: &'static str) {
+        self.numbers.extend(
+            capture
+                .records
+                .iter()
+                .filter_map(|r| DirectoryNumber::from_record(r, source)),
+        );
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DirectoryNumber> {
+        self.numbers.iter()
+    }
+
+    /// Find a number regardless of punctuation.  If nothing matches exactly, a number that was
+    /// dialed with more digits than the DMS-10 prints (or vice versa, e.g. with or without the
+    /// office code) is matched on its trailing digits, as long as at least `MIN_SUFFIX` of them
+    /// are compared and only one number matches; see `candidates`.
+    pub fn find(&self, query: &str) -> Option<&DirectoryNumber> {
+        let query = digits(query);
+        if query.is_empty() {
+            return None;
+        }
+
+        if let Some(exact) = self.numbers.iter().find(|n| n.digits() == query) {
+            return Some(exact);
+        }
+        match self.suffix_matches(&query).as_slice() {
+            [only] => Some(only),
+            // the same number in more than one capture is still only one number
+            [first, rest @ ..] if rest.iter().all(|n| n.digits() == first.digits()) => Some(first),
+            _ => None,
+        }
+    }
+
+    /// Every number that `query` could mean when it doesn't match exactly, so that an ambiguous
+    /// query can be reported with what it might have meant.
+    pub fn candidates(&self, query: &str) -> Vec<&DirectoryNumber> {
+        self.suffix_matches(&digits(query))
+    }
+
+    fn suffix_matches(&self, query: &str) -> Vec<&DirectoryNumber> {
+        self.numbers
+            .iter()
+            .filter(|n| {
+                let d = n.digits();
+                d.len().min(query.len()) >= MIN_SUFFIX
+                    && (d.ends_with(query) || query.ends_with(&d))
+            })
+            .collect()
+    }
+
+    /// Every number whose line equipment is `len`, ignoring differences in spacing and padding.
+    pub fn on_len(&self, len: &str) -> impl Iterator<Item = &DirectoryNumber> {
+        let len = inventory::location_key(len);
+        self.numbers.iter().filter(move |n| {
+            n.len
+                .as_deref()
+                .is_some_and(|l| inventory::location_key(l) == len)
+        })
+    }
+
+    /// Every number that is a member of hunt group `group`.
+    pub fn in_hunt_group<'a>(
+        &'a self,
+        group: &'a str,
+    ) -> impl Iterator<Item = &'a DirectoryNumber> {
+        self.numbers
+            .iter()
+            .filter(move |n| n.hunt_groups.iter().any(|g| g == group))
+    }
+}
+
+fn list_or_none(values: &[String]) -> String {
+    if values.is_empty() {
+        "(none)".to_owned()
+    } else {
+        values.join(", ")
+    }
+}
+
+fn digits(s: &str) -> String {
+    s.chars().filter(char::is_ascii_digit).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers() -> DirectoryNumbers {
+        let mut numbers = DirectoryNumbers::default();
+        numbers.extend(
+            &Capture::parse(
+                "    DN    5551234\n    LEN   0 2 03 05\n\n    DN    5556634\n\n    DN    5561234\n",
+            ),
+            "DN/DN.txt",
+        );
+        numbers
+    }
+
+    #[test]
+    fn exact_match_ignores_punctuation() {
+        assert_eq!(numbers().find("555-1234").unwrap().dn, "5551234");
+    }
+
+    #[test]
+    fn suffix_needs_enough_digits() {
+        let numbers = numbers();
+        assert_eq!(numbers.find("6634").unwrap().dn, "5556634");
+        assert_eq!(numbers.find("1-555-6634").unwrap().dn, "5556634");
+        assert!(numbers.find("34").is_none());
+        assert!(numbers.candidates("34").is_empty());
+    }
+
+    #[test]
+    fn ambiguous_suffix_is_not_found() {
+        let numbers = numbers();
+        assert!(numbers.find("1234").is_none());
+        let candidates: Vec<_> = numbers
+            .candidates("1234")
+            .iter()
+            .map(|n| n.dn.as_str())
+            .collect();
+        assert_eq!(candidates, ["5551234", "5561234"]);
+    }
+}
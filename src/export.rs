@@ -0,0 +1,117 @@
+//! Write parsed captures out in formats that other tools can consume directly.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::parser::{Capture, Field, Record};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Json,
+    Yaml,
+    /// One row per record and one column per prompt.  Prompts with several values have them joined
+    /// with `; ` in a single cell.
+    Csv,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+            Format::Csv => "csv",
+        }
+    }
+
+    /// The format of an export at `path`, going by its extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "json" => Some(Format::Json),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+
+    /// The path that an export of the capture at `txt` is written to, i.e. `OVLY/TYP.txt` becomes
+    /// `OVLY/TYP.json`.
+    pub fn path_for(self, txt: impl AsRef<Path>) -> PathBuf {
+        txt.as_ref().with_extension(self.extension())
+    }
+
+    /// Write `capture` next to the capture at `txt`, returning the path that was written.
+    pub fn write(self, capture: &Capture, txt: impl AsRef<Path>) -> anyhow::Result<PathBuf> {
+        let path = self.path_for(txt);
+
+        let text = match self {
+            Format::Json => serde_json::to_string_pretty(capture)? + "\n",
+            Format::Yaml => serde_yaml::to_string(capture)?,
+            Format::Csv => to_csv(capture)?,
+        };
+        std::fs::write(&path, text).with_context(|| format!("writing to {}", path.display()))?;
+
+        Ok(path)
+    }
+}
+
+/// Read an export back into a capture, e.g. one that was edited to describe how the configuration
+/// should be.  Records read this way have no line numbers.  A `.txt` capture, such as one backed up
+/// by `apply`, is read as it is.
+pub fn read(path: impl AsRef<Path>) -> anyhow::Result<Capture> {
+    let path = path.as_ref();
+    if path.extension().is_some_and(|e| e == "txt") {
+        return Capture::from_file(path);
+    }
+    let format = Format::from_path(path).with_context(|| {
+        format!(
+            "{} is not a .txt, .json, .yaml or .csv file",
+            path.display()
+        )
+    })?;
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+
+    let capture = match format {
+        Format::Json => serde_json::from_str(&text)?,
+        Format::Yaml => serde_yaml::from_str(&text)?,
+        Format::Csv => from_csv(&text)?,
+    };
+    Ok(capture)
+}
+
+fn from_csv(text: &str) -> anyhow::Result<Capture> {
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let prompts = reader.headers()?.clone();
+
+    let mut capture = Capture::default();
+    for row in reader.records() {
+        let fields = prompts
+            .iter()
+            .zip(row?.iter())
+            .filter(|(_, cell)| !cell.is_empty())
+            .map(|(prompt, cell)| Field {
+                prompt: prompt.to_owned(),
+                values: cell.split("; ").map(str::to_owned).collect(),
+            })
+            .collect();
+        capture.records.push(Record::from_fields(fields));
+    }
+    Ok(capture)
+}
+
+fn to_csv(capture: &Capture) -> anyhow::Result<String> {
+    let prompts = capture.prompts();
+    let mut writer = csv::Writer::from_writer(vec![]);
+
+    writer.write_record(&prompts)?;
+    for record in &capture.records {
+        writer.write_record(
+            prompts
+                .iter()
+                .map(|&prompt| record.get(prompt).unwrap_or_default().join("; ")),
+        )?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
@@ -1,11 +1,26 @@
-use std::{fs::File, io::Write as _};
+use std::{fs::File, io::Write as _, time::Duration};
 
 use anyhow::Context;
-use log::{debug, info};
+use dms10_config::{
+    parser::Capture,
+    script::{dmo_prompt, HASH},
+};
+use log::{debug, info, warn};
 
-use crate::{console::Console, HASH};
+use crate::console::{self, Console};
+
+/// How many times to re-run a failing Fetcher, and how long to wait before the first retry.  The
+/// delay doubles after every subsequent failure.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    pub delay: Duration,
+}
 
 pub struct Fetcher {
+    ovly: String,
+    typ: String,
+    request: &'static str,
     filename: String,
     interactions: Vec<(String, String)>,
 }
@@ -46,6 +61,9 @@ impl Fetcher {
     /// (i.e. one space too few)
     pub fn common_dmo_with_prompt(ovly: &str, typ: &str, prompt: impl Into<String>) -> Self {
         Self {
+            ovly: ovly.to_owned(),
+            typ: typ.to_owned(),
+            request: "que",
             filename: format!("{}/{}.txt", ovly.to_uppercase(), typ.to_uppercase()),
             interactions: vec![
                 ("****\n".to_owned(), HASH.to_owned()),
@@ -69,6 +87,9 @@ impl Fetcher {
     /// (i.e. one space too many)
     pub fn wide_dmo_with_prompt(ovly: &str, typ: &str, prompt: impl Into<String>) -> Self {
         Self {
+            ovly: ovly.to_owned(),
+            typ: typ.to_owned(),
+            request: "que",
             filename: format!("{}/{}.txt", ovly.to_uppercase(), typ.to_uppercase()),
             interactions: vec![
                 ("****\n".to_owned(), HASH.to_owned()),
@@ -89,6 +110,9 @@ impl Fetcher {
     ///         TYP   cnfg
     pub fn common_dmo_no_prompt(ovly: &str, typ: &str) -> Self {
         Self {
+            ovly: ovly.to_owned(),
+            typ: typ.to_owned(),
+            request: "que",
             filename: format!("{}/{}.txt", ovly.to_uppercase(), typ.to_uppercase()),
             interactions: vec![
                 ("****\n".to_owned(), HASH.to_owned()),
@@ -113,6 +137,9 @@ impl Fetcher {
     /// padded differently than all the others.
     pub fn cli(ovly: &str, cli: &str, prompt: &str) -> Self {
         Self {
+            ovly: ovly.to_owned(),
+            typ: "cli".to_owned(),
+            request: "que",
             filename: format!("{}/{}.txt", ovly.to_uppercase(), cli.to_uppercase()),
             interactions: vec![
                 ("****\n".to_owned(), HASH.to_owned()),
@@ -134,6 +161,9 @@ impl Fetcher {
     ///         EBSP  all
     pub fn trns_active(typ: &str) -> Self {
         Self {
+            ovly: "trns".to_owned(),
+            typ: typ.to_owned(),
+            request: "que",
             filename: format!("TRNS/active/{}.txt", typ.to_uppercase()),
             interactions: vec![
                 ("****\n".to_owned(), HASH.to_owned()),
@@ -154,6 +184,9 @@ impl Fetcher {
     ///         EBSP  all
     pub fn trns_inactive(typ: &str) -> Self {
         Self {
+            ovly: "trns".to_owned(),
+            typ: typ.to_owned(),
+            request: "quei",
             filename: format!("TRNS/inactive/{}.txt", typ.to_uppercase()),
             interactions: vec![
                 ("****\n".to_owned(), HASH.to_owned()),
@@ -166,8 +199,9 @@ impl Fetcher {
     }
 
     /// Fetch the configuration from the DMS-10, clean up whitespace and trailing prompts, and write
-    /// it to a filename generated from its `OVLY` and `TYP`.
-    pub async fn fetch_and_write(&self, console: &mut Console) -> anyhow::Result<()> {
+    /// it to a filename generated from its `OVLY` and `TYP`.  The text that was written is also
+    /// returned parsed into records.
+    pub async fn fetch_and_write(&self, console: &mut Console) -> anyhow::Result<Capture> {
         info!("fetching {}", self.filename);
 
         let buffer = self
@@ -226,13 +260,65 @@ impl Fetcher {
         let mut file =
             File::create(&self.filename).with_context(|| format!("opening {}", self.filename))?;
 
-        for line in lines {
+        for line in &lines {
             file.write_all(line)
                 .and_then(|_| file.write_all(b"\n"))
                 .with_context(|| format!("writing to {}", self.filename))?;
         }
 
-        Ok(())
+        Ok(Capture::parse(&String::from_utf8_lossy(
+            &lines.join(&b'\n'),
+        )))
+    }
+
+    /// Run `fetch_and_write`, and if it fails, reset the console back to the `  # ` prompt and try
+    /// again according to `policy`.  The error from the final attempt is returned if none succeed.
+    pub async fn fetch_and_write_with_retries(
+        &self,
+        console: &mut Console,
+        policy: RetryPolicy,
+    ) -> anyhow::Result<Capture> {
+        let attempts = policy.retries.saturating_add(1);
+        let mut delay = policy.delay;
+
+        for attempt in 1..=attempts {
+            let e = match self.fetch_and_write(console).await {
+                Ok(capture) => {
+                    if attempt > 1 {
+                        info!(
+                            "{} succeeded on attempt {} of {}",
+                            self.filename, attempt, attempts
+                        );
+                    }
+                    return Ok(capture);
+                }
+                // there's nothing left to retry on
+                Err(e) if console::is_disconnected(&e) => return Err(e),
+                Err(e) if attempt == attempts => {
+                    return Err(e).with_context(|| format!("after {} attempt(s)", attempts))
+                }
+                Err(e) => e,
+            };
+
+            warn!(
+                "attempt {} of {} for {} failed: {:#}; retrying in {:?}",
+                attempt, attempts, self.filename, e, delay
+            );
+            tokio::time::sleep(delay).await;
+            delay = delay.saturating_mul(2);
+
+            // get out of whatever overlay or prompt the failed attempt left us in
+            console
+                .send(b"****\n")
+                .await
+                .context("sending **** before retrying")?;
+            console
+                .run_until_human_prompt(HASH)
+                .await
+                .context("waiting for the # prompt before retrying")?;
+        }
+
+        unreachable!("the last attempt always returns");
     }
 
     /// Get the filename that is generated from `OVLY` and `TYP`.  This can be used to uniquely
@@ -241,6 +327,27 @@ impl Fetcher {
         &self.filename
     }
 
+    /// Get the overlay (e.g. `net`) that this Fetcher runs in.
+    pub fn ovly(&self) -> &str {
+        &self.ovly
+    }
+
+    /// Get the answer to the `TYP` prompt.
+    pub fn typ(&self) -> &str {
+        &self.typ
+    }
+
+    /// Get the answer to the `REQ` prompt, i.e. `que` or `quei`.
+    pub fn request(&self) -> &str {
+        self.request
+    }
+
+    /// Get the sequence of (bytes to send, prompt to wait for) that makes up this Fetcher's dialog
+    /// with the DMS-10.
+    pub fn interactions(&self) -> &[(String, String)] {
+        &self.interactions
+    }
+
     async fn fetch(&self, console: &mut Console) -> anyhow::Result<Vec<u8>> {
         let mut output = vec![];
 
@@ -259,7 +366,3 @@ impl Fetcher {
         Ok(output)
     }
 }
-
-fn dmo_prompt(prompt: &str) -> String {
-    format!("    {:4}  ", prompt)
-}
@@ -0,0 +1,106 @@
+use anyhow::Context;
+use glob::{MatchOptions, Pattern};
+
+use crate::fetcher::Fetcher;
+
+// `*` should only match within a single path component, so `TRNS/*` doesn't also pull in every
+// table under `TRNS/active/` and `TRNS/inactive/`.
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Selects which Fetchers to run, based on the filenames and overlays given on the command line.
+pub struct Filter {
+    include: Vec<Pattern>,
+    ovly: Vec<String>,
+    exclude: Vec<Pattern>,
+}
+
+impl Filter {
+    /// `include` and `exclude` are glob patterns on the target filename, e.g. `NET/*` or
+    /// `TRNS/*/ADDR.txt`.  `ovly` is a list of overlay names, e.g. `net`.  Empty `include` and
+    /// `ovly` lists select everything.
+    pub fn new(include: &[String], ovly: &[String], exclude: &[String]) -> anyhow::Result<Self> {
+        let compile = |patterns: &[String]| -> anyhow::Result<Vec<Pattern>> {
+            patterns
+                .iter()
+                .map(|p| Pattern::new(p).with_context(|| format!("invalid pattern {:?}", p)))
+                .collect()
+        };
+
+        Ok(Self {
+            include: compile(include)?,
+            ovly: ovly.iter().map(|o| o.to_lowercase()).collect(),
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// Keep only the Fetchers that this filter selects.  It is an error for any individual pattern
+    /// or overlay to not match any Fetcher at all, since that is almost certainly a typo.
+    pub fn select(&self, fetchers: Vec<Fetcher>) -> anyhow::Result<Vec<Fetcher>> {
+        let mut unmatched: Vec<String> = vec![];
+        for pattern in self.include.iter().chain(&self.exclude) {
+            if !fetchers
+                .iter()
+                .any(|f| pattern.matches_with(f.filename(), MATCH_OPTIONS))
+            {
+                unmatched.push(pattern.as_str().to_owned());
+            }
+        }
+        for ovly in &self.ovly {
+            if !fetchers.iter().any(|f| f.ovly() == ovly) {
+                unmatched.push(format!("--ovly {}", ovly));
+            }
+        }
+
+        if !unmatched.is_empty() {
+            anyhow::bail!(
+                "{} did not match anything.  Valid names are:\n{}",
+                unmatched.join(", "),
+                valid_names(&fetchers)
+            );
+        }
+
+        let selected: Vec<Fetcher> = fetchers.into_iter().filter(|f| self.matches(f)).collect();
+        if selected.is_empty() {
+            anyhow::bail!("the combination of filters did not select anything to fetch");
+        }
+
+        Ok(selected)
+    }
+
+    fn matches(&self, fetcher: &Fetcher) -> bool {
+        let filename = fetcher.filename();
+
+        // a fetcher is included if it matches *either* a filename or an overlay, so that
+        // `--ovly net CPK/PACK.txt` does what it looks like.
+        let included = (self.include.is_empty() && self.ovly.is_empty())
+            || self
+                .include
+                .iter()
+                .any(|p| p.matches_with(filename, MATCH_OPTIONS))
+            || self.ovly.iter().any(|o| o == fetcher.ovly());
+
+        included
+            && !self
+                .exclude
+                .iter()
+                .any(|p| p.matches_with(filename, MATCH_OPTIONS))
+    }
+}
+
+fn valid_names(fetchers: &[Fetcher]) -> String {
+    let mut names: Vec<String> = fetchers
+        .iter()
+        .map(|f| format!("  {}", f.filename()))
+        .collect();
+
+    let mut ovlys: Vec<&str> = fetchers.iter().map(|f| f.ovly()).collect();
+    ovlys.sort_unstable();
+    ovlys.dedup();
+    names.push(format!("and overlays: {}", ovlys.join(", ")));
+
+    names.join("\n")
+}
@@ -0,0 +1,206 @@
+//! Hunt groups (`HUNT/DNH.txt`, keyed by `HTGP`) and EBS groups (`HUNT/EBS.txt`, keyed by
+//! `EBSG`).
+
+use std::{fmt, path::Path};
+
+use serde::Serialize;
+
+use crate::{
+    dn::{DirectoryNumber, DirectoryNumbers},
+    parser::{Capture, Record},
+};
+
+// The prompts for each attribute, in order of preference where the two TYPs spell the same thing
+// differently.
+const HUNT_TYPE: &[&str] = &["HTYP", "TYPE", "HUNT"];
+const MEMBERS: &[&str] = &["DN", "MEMB", "MBR"];
+const OVERFLOW: &[&str] = &["OVFL", "OVF", "OFDN"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Kind {
+    /// A directory number hunt group.
+    Dnh,
+    /// An electronic business set group.
+    Ebs,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 2] = [Kind::Dnh, Kind::Ebs];
+
+    /// The prompt that each group in the capture is keyed by.
+    pub fn prompt(self) -> &'static str {
+        match self {
+            Kind::Dnh => "HTGP",
+            Kind::Ebs => "EBSG",
+        }
+    }
+
+    pub fn capture(self) -> &'static str {
+        match self {
+            Kind::Dnh => "HUNT/DNH.txt",
+            Kind::Ebs => "HUNT/EBS.txt",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HuntGroup {
+    pub kind: Kind,
+    pub number: String,
+    pub hunt_type: Option<String>,
+    /// Member directory numbers, in the order they are hunted.
+    pub members: Vec<String>,
+    /// Where calls go when every member is busy.
+    pub overflow: Option<String>,
+    pub line: usize,
+    pub record: Record,
+}
+
+impl HuntGroup {
+    fn from_record(kind: Kind, record: &Record) -> Option<Self> {
+        Some(Self {
+            kind,
+            number: record.first(kind.prompt())?.to_owned(),
+            hunt_type: record.first_any(HUNT_TYPE).map(str::to_owned),
+            members: record.get_any(MEMBERS).unwrap_or_default().to_vec(),
+            overflow: record.first_any(OVERFLOW).map(str::to_owned),
+            line: record.line(),
+            record: record.clone(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct HuntGroups {
+    pub groups: Vec<HuntGroup>,
+}
+
+impl HuntGroups {
+    /// Read every hunt and EBS group from the captures in `dir`.  Captures that don't exist are
+    /// skipped.
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut groups = vec![];
+        for kind in Kind::ALL {
+            if let Some(capture) = Capture::load(dir.as_ref(), kind.capture())? {
+                groups.extend(
+                    capture
+                        .records
+                        .iter()
+                        .filter_map(|r| HuntGroup::from_record(kind, r)),
+                );
+            }
+        }
+        Ok(Self { groups })
+    }
+
+    pub fn get(&self, kind: Kind, number: &str) -> Option<&HuntGroup> {
+        self.groups
+            .iter()
+            .find(|g| g.kind == kind && g.number == number)
+    }
+
+    pub fn of_kind(&self, kind: Kind) -> impl Iterator<Item = &HuntGroup> {
+        self.groups.iter().filter(move |g| g.kind == kind)
+    }
+
+    /// Pair every group's members with their entries in the DN captures.
+    pub fn resolve<'a>(&'a self, numbers: &'a DirectoryNumbers) -> Vec<ResolvedHuntGroup<'a>> {
+        self.groups
+            .iter()
+            .map(|group| ResolvedHuntGroup {
+                group,
+                members: group
+                    .members
+                    .iter()
+                    .map(|m| (m.as_str(), numbers.find(m)))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResolvedHuntGroup<'a> {
+    pub group: &'a HuntGroup,
+    /// Each member as listed in the group, and its directory number if it's in the DN captures.
+    pub members: Vec<(&'a str, Option<&'a DirectoryNumber>)>,
+}
+
+impl fmt::Display for ResolvedHuntGroup<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let group = self.group;
+        write!(f, "{} {}", group.kind.prompt(), group.number)?;
+        if let Some(hunt_type) = &group.hunt_type {
+            write!(f, "  type {}", hunt_type)?;
+        }
+        if let Some(overflow) = &group.overflow {
+            write!(f, "  overflow {}", overflow)?;
+        }
+        writeln!(f, "  ({}:{})", group.kind.capture(), group.line)?;
+
+        if self.members.is_empty() {
+            writeln!(f, "    (no members)")?;
+        }
+        for (i, (member, dn)) in self.members.iter().enumerate() {
+            match dn {
+                Some(dn) => writeln!(
+                    f,
+                    "    {:2}. {:10}  LEN {:12}  {}",
+                    i + 1,
+                    member,
+                    dn.len.as_deref().unwrap_or("(none)"),
+                    dn.class.as_deref().unwrap_or("")
+                )?,
+                None => writeln!(
+                    f,
+                    "    {:2}. {:10}  (not in the DN captures)",
+                    i + 1,
+                    member
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn members_keep_their_order_and_resolve_against_the_dns() {
+        let mut numbers = DirectoryNumbers::default();
+        numbers.extend(
+            &Capture::parse("    DN    5551234\n    LEN   0 2 03 05\n\n    DN    5556634\n"),
+            "DN/DN.txt",
+        );
+        let capture = Capture::parse(
+            "    HTGP  12\n    HTYP  CIRC\n    DN    5556634\n          555-1234\n          5559999\n",
+        );
+        let hunt = HuntGroups {
+            groups: capture
+                .records
+                .iter()
+                .filter_map(|r| HuntGroup::from_record(Kind::Dnh, r))
+                .collect(),
+        };
+
+        let resolved = hunt.resolve(&numbers);
+        assert_eq!(resolved.len(), 1);
+        let members: Vec<(&str, Option<&str>)> = resolved[0]
+            .members
+            .iter()
+            .map(|(member, dn)| (*member, dn.map(|d| d.dn.as_str())))
+            .collect();
+        assert_eq!(
+            members,
+            [
+                ("5556634", Some("5556634")),
+                ("555-1234", Some("5551234")),
+                ("5559999", None),
+            ]
+        );
+        assert!(hunt.get(Kind::Dnh, "12").is_some());
+        assert!(hunt.get(Kind::Ebs, "12").is_none());
+    }
+}
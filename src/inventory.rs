@@ -0,0 +1,336 @@
+//! The physical hardware described by the `CPK` and `NET` captures, arranged as a tree of bays,
+//! shelves, slots, packs and the circuits on them.
+//!
+//! Every capture here is keyed by an equipment location like `0 1 05`, which is read as bay, shelf
+//! and slot.  `CPK/PACK.txt` names the packs; the other captures either describe a pack in more
+//! detail (their key is exactly a pack's location) or describe circuits on it (their key starts
+//! with a pack's location).  Anything that refers to a location with no pack is given a pack of its
+//! own, named after the capture it came from.
+
+use std::{fmt, fmt::Write as _, path::Path};
+
+use serde::Serialize;
+
+use crate::{
+    parser::{Capture, Record},
+    site::{self, escape},
+};
+
+/// The captures that are read, in order.  `CPK/PACK.txt` must be first so that every other capture
+/// can find the packs it refers to.
+pub const CAPTURES: &[&str] = &[
+    "CPK/PACK.txt",
+    "CPK/LPK.txt",
+    "CPK/SLC.txt",
+    "CPK/SLPK.txt",
+    "CPK/DCM.txt",
+    "CPK/IDTL.txt",
+    "NET/DS1L.txt",
+    "NET/D1PK.txt",
+    "NET/DSI.txt",
+    "NET/IFPK.txt",
+];
+
+const PACK_TYPE: &[&str] = &["PTYP", "TYPE", "PEC"];
+
+/// How the inventory can be printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Text,
+    Html,
+    Json,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Source {
+    pub file: &'static str,
+    pub line: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Circuit {
+    pub location: String,
+    pub source: Source,
+    pub record: Record,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Pack {
+    pub location: String,
+    pub pack_type: Option<String>,
+    /// Every record that describes the pack itself.
+    pub sources: Vec<Source>,
+    pub circuits: Vec<Circuit>,
+    #[serde(skip)]
+    tokens: Vec<String>,
+    /// [`location_key`] of the location, which is what other locations are matched against.
+    #[serde(skip)]
+    key: Vec<String>,
+}
+
+impl Pack {
+    pub fn bay(&self) -> &str {
+        self.tokens.first().map(String::as_str).unwrap_or("?")
+    }
+
+    pub fn shelf(&self) -> &str {
+        self.tokens.get(1).map(String::as_str).unwrap_or("?")
+    }
+
+    /// The slot, plus anything after it in the location for packs that are addressed more finely.
+    pub fn slot(&self) -> String {
+        match self.tokens.get(2..) {
+            Some(rest) if !rest.is_empty() => rest.join(" "),
+            _ => "?".to_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Shelf {
+    pub number: String,
+    pub packs: Vec<Pack>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Bay {
+    pub number: String,
+    pub shelves: Vec<Shelf>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Inventory {
+    pub bays: Vec<Bay>,
+}
+
+impl Inventory {
+    /// Build the inventory from the captures in `dir`.  Captures that don't exist are skipped.
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut captures = vec![];
+        for &file in CAPTURES {
+            if let Some(capture) = Capture::load(dir.as_ref(), file)? {
+                captures.push((file, capture));
+            }
+        }
+        Ok(Self::from_captures(&captures))
+    }
+
+    /// Build the inventory from captures already read, given in [`CAPTURES`] order.
+    fn from_captures(captures: &[(&'static str, Capture)]) -> Self {
+        let mut packs: Vec<Pack> = vec![];
+        for (file, capture) in captures {
+            for record in &capture.records {
+                let Some((_, location)) = record.key() else {
+                    continue;
+                };
+                add_record(&mut packs, file, record, location);
+            }
+        }
+
+        packs.sort_by_key(|p| sort_key(&p.tokens));
+
+        let mut inventory = Inventory::default();
+        for pack in packs {
+            if inventory.bays.last().is_none_or(|b| b.number != pack.bay()) {
+                inventory.bays.push(Bay {
+                    number: pack.bay().to_owned(),
+                    shelves: vec![],
+                });
+            }
+            let bay = inventory.bays.last_mut().expect("just pushed");
+            if bay.shelves.last().is_none_or(|s| s.number != pack.shelf()) {
+                bay.shelves.push(Shelf {
+                    number: pack.shelf().to_owned(),
+                    packs: vec![],
+                });
+            }
+            bay.shelves
+                .last_mut()
+                .expect("just pushed")
+                .packs
+                .push(pack);
+        }
+        inventory
+    }
+
+    pub fn packs(&self) -> impl Iterator<Item = &Pack> {
+        self.bays
+            .iter()
+            .flat_map(|b| &b.shelves)
+            .flat_map(|s| &s.packs)
+    }
+
+    /// The pack that equipment at `location` (e.g. a line's LEN) is on.
+    pub fn pack_for(&self, location: &str) -> Option<&Pack> {
+        let key = location_key(location);
+        self.packs()
+            .filter(|p| !p.key.is_empty() && key.starts_with(&p.key))
+            .max_by_key(|p| p.key.len())
+    }
+
+    /// Render the inventory as a standalone HTML page.
+    pub fn to_html(&self) -> String {
+        site::document("DMS-10 hardware inventory", &self.html_body())
+    }
+
+    /// The inventory as HTML tables, one per shelf, with each pack's row anchored by
+    /// [`site::pack_anchor`].
+    pub fn html_body(&self) -> String {
+        let mut html = String::new();
+        if self.bays.is_empty() {
+            html.push_str("<p>(no packs captured)</p>\n");
+        }
+        for bay in &self.bays {
+            let _ = writeln!(html, "<h2>Bay {}</h2>", escape(&bay.number));
+            for shelf in &bay.shelves {
+                let _ = writeln!(html, "<h3>Shelf {}</h3>\n<table>", escape(&shelf.number));
+                html.push_str(
+                    "<tr><th>Slot</th><th>Pack</th><th>Circuits</th><th>Source</th></tr>\n",
+                );
+                for pack in &shelf.packs {
+                    let sources: Vec<String> = pack
+                        .sources
+                        .iter()
+                        .map(|s| format!("{}:{}", s.file, s.line))
+                        .collect();
+                    let _ = writeln!(
+                        html,
+                        "<tr id=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                        site::pack_anchor(&pack.location),
+                        escape(&pack.slot()),
+                        escape(pack.pack_type.as_deref().unwrap_or("")),
+                        pack.circuits.len(),
+                        escape(&sources.join(", "))
+                    );
+                }
+                html.push_str("</table>\n");
+            }
+        }
+        html
+    }
+}
+
+impl fmt::Display for Inventory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for bay in &self.bays {
+            writeln!(f, "bay {}", bay.number)?;
+            for shelf in &bay.shelves {
+                writeln!(f, "    shelf {}", shelf.number)?;
+                for pack in &shelf.packs {
+                    writeln!(
+                        f,
+                        "        slot {:6}  {:8}  {} circuit(s)",
+                        pack.slot(),
+                        pack.pack_type.as_deref().unwrap_or("?"),
+                        pack.circuits.len()
+                    )?;
+                    for circuit in &pack.circuits {
+                        writeln!(
+                            f,
+                            "            {:14}  ({}:{})",
+                            circuit.location, circuit.source.file, circuit.source.line
+                        )?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn add_record(packs: &mut Vec<Pack>, file: &'static str, record: &Record, location: &str) {
+    let tokens: Vec<String> = location.split_whitespace().map(str::to_owned).collect();
+    let key = location_key(location);
+    let source = Source {
+        file,
+        line: record.line(),
+    };
+
+    if let Some(pack) = packs.iter_mut().find(|p| p.key == key) {
+        // more detail about a pack we already know about
+        if pack.pack_type.is_none() {
+            pack.pack_type = record.first_any(PACK_TYPE).map(str::to_owned);
+        }
+        pack.sources.push(source);
+        return;
+    }
+
+    if let Some(pack) = packs
+        .iter_mut()
+        .filter(|p| !p.key.is_empty() && key.starts_with(&p.key))
+        .max_by_key(|p| p.key.len())
+    {
+        pack.circuits.push(Circuit {
+            location: tokens.join(" "),
+            source,
+            record: record.clone(),
+        });
+        return;
+    }
+
+    // e.g. `CPK/LPK.txt` → `LPK`, for a pack that `CPK/PACK.txt` didn't mention
+    let from_file = file
+        .rsplit('/')
+        .next()
+        .and_then(|f| f.strip_suffix(".txt"))
+        .unwrap_or(file);
+    packs.push(Pack {
+        location: tokens.join(" "),
+        pack_type: Some(record.first_any(PACK_TYPE).unwrap_or(from_file).to_owned()),
+        sources: vec![source],
+        circuits: vec![],
+        tokens,
+        key,
+    });
+}
+
+/// A location with each numeric token compared by value, so that `0 2 03 05` and `0 2 3 5` are the
+/// same circuit.
+pub(crate) fn location_key(location: &str) -> Vec<String> {
+    location
+        .split_whitespace()
+        .map(|t| match t.parse::<u64>() {
+            Ok(n) => n.to_string(),
+            Err(_) => t.to_owned(),
+        })
+        .collect()
+}
+
+/// Sort numerically where the parts of a location are numbers, so that slot 10 comes after slot 9.
+pub(crate) fn sort_key<S: AsRef<str>>(tokens: &[S]) -> Vec<(u64, String)> {
+    tokens
+        .iter()
+        .map(|t| {
+            (
+                t.as_ref().parse().unwrap_or(u64::MAX),
+                t.as_ref().to_owned(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padded_and_unpadded_lens_are_on_the_same_pack() {
+        let packs = Capture::parse("    LOC   0 2 03\n    PACK  LPK\n");
+        let lines = Capture::parse(
+            "    LEN   0 2 03 05\n    LCC   1FR\n    LEN   0 2 3 7\n    LCC   1FR\n",
+        );
+        let inventory =
+            Inventory::from_captures(&[("CPK/PACK.txt", packs), ("CPK/LPK.txt", lines)]);
+
+        let all: Vec<&Pack> = inventory.packs().collect();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].circuits.len(), 2);
+        for len in ["0 2 03 05", "0 2 3 7", "00 02 003 7"] {
+            assert_eq!(
+                inventory.pack_for(len).map(|p| p.location.as_str()),
+                Some("0 2 03")
+            );
+        }
+        assert!(inventory.pack_for("0 2 4 7").is_none());
+    }
+}
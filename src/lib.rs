@@ -0,0 +1,19 @@
+//! Library half of dms10_config: everything that works on captured DMS-10 output without needing a
+//! connection to the switch.
+
+pub mod changes;
+pub mod diff;
+pub mod dn;
+pub mod export;
+pub mod hunt;
+pub mod inventory;
+pub mod lint;
+pub mod numbering;
+pub mod occupancy;
+pub mod parser;
+pub mod rebuild;
+pub mod routing;
+pub mod script;
+pub mod site;
+pub mod translations;
+pub mod trunk;
@@ -0,0 +1,248 @@
+//! Cross-reference checks between captures: things that refer to other things which don't exist,
+//! or exist but can't do anything useful.
+
+use std::{fmt, path::Path};
+
+use serde::Serialize;
+
+use crate::{
+    dn::DirectoryNumbers,
+    hunt::{self, HuntGroups},
+    inventory,
+    parser::{Capture, Record},
+    routing::{Kind, Routing, Target},
+    translations::{Next, Set, Translations},
+    trunk::{self, Trunks},
+};
+
+pub const PACK_CAPTURE: &str = "CPK/PACK.txt";
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Finding {
+    /// The capture the problem was found in, e.g. `DN/DN.txt`.
+    pub file: String,
+    pub line: usize,
+    /// The record the problem is in, e.g. `DN  5551234`.
+    pub key: String,
+    pub message: String,
+}
+
+impl Finding {
+    fn new(file: &str, record: &Record, message: String) -> Self {
+        let (prompt, value) = record.key().unwrap_or_default();
+        Self {
+            file: file.to_owned(),
+            line: record.line(),
+            key: format!("{}  {}", prompt, value),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.file, self.line, self.key, self.message
+        )
+    }
+}
+
+/// Run every check against the captures in `dir`.  Checks whose captures weren't fetched are
+/// skipped, since there's nothing to compare against.
+pub fn lint(dir: impl AsRef<Path>) -> anyhow::Result<Vec<Finding>> {
+    let dir = dir.as_ref();
+    let numbers = DirectoryNumbers::load(dir)?;
+    let trunks = Trunks::load(dir)?;
+    let routing = Routing::load(dir)?;
+    let hunt = HuntGroups::load(dir)?;
+    let packs = Capture::load(dir, PACK_CAPTURE)?;
+
+    let mut findings = vec![];
+
+    // directory numbers
+    for dn in numbers.iter() {
+        if dn.len.is_none() {
+            findings.push(Finding::new(
+                dn.source,
+                &dn.record,
+                "has no line equipment".to_owned(),
+            ));
+        }
+        if hunt.of_kind(hunt::Kind::Dnh).next().is_some() {
+            for group in &dn.hunt_groups {
+                if hunt.get(hunt::Kind::Dnh, group).is_none() {
+                    findings.push(Finding::new(
+                        dn.source,
+                        &dn.record,
+                        format!(
+                            "refers to hunt group {}, which is not in {}",
+                            group,
+                            hunt::Kind::Dnh.capture()
+                        ),
+                    ));
+                }
+            }
+        }
+        if let (Some(packs), Some(len)) = (&packs, &dn.len) {
+            if !on_known_pack(packs, len) {
+                findings.push(Finding::new(
+                    dn.source,
+                    &dn.record,
+                    format!(
+                        "line equipment {} is not on any pack in {}",
+                        len, PACK_CAPTURE
+                    ),
+                ));
+            }
+        }
+    }
+
+    // hunt group members
+    if numbers.iter().next().is_some() {
+        for group in &hunt.groups {
+            for member in &group.members {
+                if numbers.find(member).is_none() {
+                    findings.push(Finding::new(
+                        group.kind.capture(),
+                        &group.record,
+                        format!("member {} is not a known directory number", member),
+                    ));
+                }
+            }
+        }
+    }
+
+    // trunks
+    for trunk in &trunks.trunks {
+        match &trunk.group {
+            Some(group)
+                if !trunks.groups.is_empty() && trunks.group(trunk.kind, group).is_none() =>
+            {
+                findings.push(Finding::new(
+                    trunk.source,
+                    &trunk.record,
+                    format!(
+                        "is a member of {} {}, which is not in {}",
+                        trunk.kind,
+                        group,
+                        trunk.kind.capture()
+                    ),
+                ))
+            }
+            None => findings.push(Finding::new(
+                trunk.source,
+                &trunk.record,
+                "is not a member of any trunk group".to_owned(),
+            )),
+            _ => {}
+        }
+        if let Some(packs) = &packs {
+            if !on_known_pack(packs, &trunk.equipment) {
+                findings.push(Finding::new(
+                    trunk.source,
+                    &trunk.record,
+                    format!("is not on any pack in {}", PACK_CAPTURE),
+                ));
+            }
+        }
+    }
+
+    // routes
+    for entry in &routing.entries {
+        let source = entry.kind.capture();
+        for target in &entry.targets {
+            match target {
+                Target::TrunkGroup(number) if !trunks.groups.is_empty() => {
+                    if trunks.group(trunk::Kind::Tg, number).is_none() {
+                        findings.push(Finding::new(
+                            &source,
+                            &entry.record,
+                            format!("uses TG {}, which is not in any TG capture", number),
+                        ));
+                    } else if !trunks.trunks.is_empty()
+                        && trunks.members(trunk::Kind::Tg, number).is_empty()
+                    {
+                        findings.push(Finding::new(
+                            &source,
+                            &entry.record,
+                            format!("uses TG {}, which has no member trunks", number),
+                        ));
+                    }
+                }
+                Target::Entry(kind, number) if routing.get(*kind, number).is_none() => {
+                    findings.push(Finding::new(
+                        &source,
+                        &entry.record,
+                        format!(
+                            "refers to {} {}, which is not in {}",
+                            kind,
+                            number,
+                            kind.capture()
+                        ),
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // translations
+    for set in [Set::Active, Set::Inactive] {
+        let translations = Translations::load(dir, set)?;
+        for entry in &translations.entries {
+            let missing = match &entry.next {
+                Some(Next::Translator(table, translator)) => {
+                    let mut entries = translations.table(*table).peekable();
+                    let captured = entries.peek().is_some();
+                    (captured && !entries.any(|e| &e.translator == translator))
+                        .then(|| format!("{} {}", table, translator))
+                }
+                Some(Next::Route(route)) => missing_route(&routing, Kind::Rout, route),
+                Some(Next::Destination(dest)) => missing_route(&routing, Kind::Dest, dest),
+                _ => None,
+            };
+            if let Some(missing) = missing {
+                findings.push(Finding::new(
+                    &entry.source,
+                    &entry.record,
+                    format!("leads to {}, which was not captured", missing),
+                ));
+            }
+        }
+    }
+
+    Ok(findings)
+}
+
+fn missing_route(routing: &Routing, kind: Kind, number: &str) -> Option<String> {
+    let captured = routing.entries.iter().any(|e| e.kind == kind);
+    (captured && routing.get(kind, number).is_none()).then(|| format!("{} {}", kind, number))
+}
+
+/// Whether equipment like `0 3 05 1` is on a pack whose location (e.g. `0 3 05`) is in the pack
+/// capture.  Numbers are compared by value, so `0 2 3 7` is on pack `0 2 03`.
+fn on_known_pack(packs: &Capture, equipment: &str) -> bool {
+    let equipment = inventory::location_key(equipment);
+    packs.records.iter().any(|r| {
+        let Some((_, location)) = r.key() else {
+            return false;
+        };
+        let location = inventory::location_key(location);
+        !location.is_empty() && equipment.starts_with(&location)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padded_pack_covers_unpadded_equipment() {
+        let packs = Capture::parse("    LOC   0 2 03\n    PACK  LPK\n");
+        assert!(on_known_pack(&packs, "0 2 3 7"));
+        assert!(on_known_pack(&packs, "0 2 03 05"));
+        assert!(!on_known_pack(&packs, "0 2 4 7"));
+    }
+}
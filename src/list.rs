@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Serialize;
+
+use dms10_config::export;
+
+use crate::fetcher::Fetcher;
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    filename: &'a str,
+    ovly: &'a str,
+    typ: &'a str,
+    request: &'a str,
+    interactions: Vec<Interaction<'a>>,
+}
+
+#[derive(Serialize)]
+struct Interaction<'a> {
+    send: &'a str,
+    expect: &'a str,
+}
+
+impl<'a> From<&'a Fetcher> for Entry<'a> {
+    fn from(fetcher: &'a Fetcher) -> Self {
+        Self {
+            filename: fetcher.filename(),
+            ovly: fetcher.ovly(),
+            typ: fetcher.typ(),
+            request: fetcher.request(),
+            interactions: fetcher
+                .interactions()
+                .iter()
+                .map(|(send, expect)| Interaction { send, expect })
+                .collect(),
+        }
+    }
+}
+
+/// Print the catalog of Fetchers to stdout, either as a human-readable script of each dialog or as
+/// a JSON array.
+pub fn print(fetchers: &[Fetcher], json: bool) -> anyhow::Result<()> {
+    if json {
+        let entries: Vec<Entry> = fetchers.iter().map(Entry::from).collect();
+        let json = serde_json::to_string_pretty(&entries).context("serializing catalog")?;
+        println!("{}", json);
+        return Ok(());
+    }
+
+    for fetcher in fetchers {
+        println!(
+            "{}  OVLY {}  TYP {}  REQ {}",
+            fetcher.filename(),
+            fetcher.ovly(),
+            fetcher.typ(),
+            fetcher.request()
+        );
+        print_interactions(fetcher);
+    }
+
+    Ok(())
+}
+
+/// Print what `fetch` would do for each Fetcher, without touching the DMS-10: every byte that
+/// would be sent, every prompt that would be waited for, and the files that would be written.
+pub fn print_plan(fetchers: &[Fetcher], exports: &[export::Format]) {
+    for fetcher in fetchers {
+        println!("{}", fetcher.filename());
+        print_interactions(fetcher);
+
+        let path = Path::new(fetcher.filename());
+        match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() && !dir.is_dir() => println!(
+                "    write {} (but directory {} does not exist)",
+                path.display(),
+                dir.display()
+            ),
+            _ => println!("    write {}", path.display()),
+        }
+        for format in exports {
+            println!("    write {}", format.path_for(path).display());
+        }
+    }
+}
+
+fn print_interactions(fetcher: &Fetcher) {
+    for (send, expect) in fetcher.interactions() {
+        // quote everything, since the trailing whitespace in the prompts is significant
+        println!(
+            "    send \"{}\"  expect \"{}\"",
+            send.as_bytes().escape_ascii(),
+            expect.as_bytes().escape_ascii()
+        );
+    }
+}
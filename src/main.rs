@@ -1,32 +1,170 @@
-use std::{collections::HashSet, time::Duration};
+use std::{path::PathBuf, process::ExitCode, time::Duration};
 
 use anyhow::Context;
 use clap::Parser;
-use console::Console;
-use fetcher::Fetcher;
-use log::{debug, info, warn};
+use console::{Access, Console};
+use dms10_config::{
+    changes,
+    diff::{CaptureDiff, CaptureSet, SetDiff},
+    dn::{self, DirectoryNumbers},
+    export,
+    hunt::{self, HuntGroups},
+    inventory::{self, Inventory},
+    lint,
+    numbering::NumberingPlan,
+    occupancy::Occupancy,
+    parser::Capture,
+    rebuild,
+    routing::{Origin, Routing, Simulation},
+    script::{Script, HASH},
+    site::Site,
+    translations::{self, Translations},
+    trunk::{self, Trunks},
+};
+use fetcher::{Fetcher, RetryPolicy};
+use filter::Filter;
+use log::{debug, error, info};
+use summary::Summary;
 use tokio::select;
 
+mod apply;
 mod console;
 mod fetcher;
-
-static HASH: &str = "  # ";
+mod filter;
+mod list;
+mod summary;
 
 #[derive(Debug, clap::Parser)]
 struct Config {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Fetch resources from the DMS-10 and write each one to its own file
+    Fetch(FetchArgs),
+    /// List every resource that can be fetched, along with its dialog with the DMS-10
+    List(ListArgs),
+    /// Convert existing captures into structured formats, written next to each capture
+    Export(ExportArgs),
+    /// Look up directory numbers in the DN captures
+    Dn(DnArgs),
+    /// Show trunk groups with their member trunks from the TG and TRK captures
+    Tg(TgArgs),
+    /// Show hunt and EBS groups with their members resolved against the DN captures
+    Hunt(HuntArgs),
+    /// Show the hardware in the CPK and NET captures as a tree of bays, shelves, slots and packs
+    Inventory(InventoryArgs),
+    /// Show which circuits on each line pack are assigned to a DN and which are spare
+    Occupancy(OccupancyArgs),
+    /// Show which line numbers in each office code are assigned, reserved or free
+    Numbering(NumberingArgs),
+    /// Render the captures as a cross-linked static website
+    Html(HtmlArgs),
+    /// Generate DMO scripts of NEW requests that would recreate the captured configuration
+    Rebuild(RebuildArgs),
+    /// Generate a DMO script of OUT, CHG and NEW requests that turns a capture into a desired state
+    Changes(ChangesArgs),
+    /// Run a DMO script against the DMS-10, then re-fetch the overlays it changed
+    Apply(ApplyArgs),
+    /// Trace a dialed number through the captured TRNS translators
+    Translate(TranslateArgs),
+    /// Compare the inactive TRNS tables against the active ones, entry by entry
+    TrnsDiff(TrnsDiffArgs),
+    /// Compare two sets of captures record by record; exits 1 if they differ
+    Diff(DiffArgs),
+    /// Check the captures for dangling references and inconsistencies; exits 1 if any are found
+    Lint(LintArgs),
+    /// Simulate how a number dialed from a line or trunk group is translated and routed
+    Route(RouteArgs),
+}
+
+#[derive(Debug, clap::Args)]
+struct FilterArgs {
+    #[arg(
+        help = "resources to select.  Specify the target filename or a glob, e.g. NET/DSLK.txt or NET/*"
+    )]
+    files: Vec<String>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "also select every resource in these overlays, e.g. --ovly net,trk"
+    )]
+    ovly: Vec<String>,
+
+    #[arg(
+        long,
+        help = "glob pattern of resources to leave out, e.g. TRNS/inactive/*; may be repeated"
+    )]
+    exclude: Vec<String>,
+}
+
+impl FilterArgs {
+    fn select(&self) -> anyhow::Result<Vec<Fetcher>> {
+        Filter::new(&self.files, &self.ovly, &self.exclude)?.select(all_fetchers())
+    }
+}
+
+#[derive(Debug, clap::Args)]
+struct LoginArgs {
     #[arg(long, default_value = "10.27.20.179")]
     hostname: String,
 
     #[arg(skip)]
     password: String,
+}
+
+#[derive(Debug, clap::Args)]
+struct FetchArgs {
+    #[command(flatten)]
+    login: LoginArgs,
+
+    #[command(flatten)]
+    filter: FilterArgs,
 
     #[arg(
-        help = "resources to fetch from the DMS-10.  Specify the target filename, e.g. NET/DSLK.txt"
+        long,
+        help = "keep fetching the remaining resources after one fails, instead of stopping"
     )]
-    files: Vec<String>,
+    keep_going: bool,
+
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "number of times to retry a resource that fails to fetch"
+    )]
+    retries: u32,
+
+    #[arg(
+        long,
+        default_value_t = 5,
+        help = "seconds to wait before the first retry; doubles after each failed retry"
+    )]
+    retry_delay: u64,
+
+    #[arg(
+        long,
+        help = "seconds without reaching an expected prompt before a fetch is considered failed"
+    )]
+    stall_timeout: Option<u64>,
+
+    #[arg(
+        long,
+        help = "print what would be sent to the DMS-10 and which files would be written, without connecting"
+    )]
+    dry_run: bool,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "also write each resource as structured records, e.g. --export json,csv"
+    )]
+    export: Vec<export::Format>,
 }
 
-impl Config {
+impl LoginArgs {
     fn read_password(mut self) -> Self {
         self.password = if let Ok(x) = std::env::var("DMS10_PASSWORD") {
             x
@@ -39,17 +177,900 @@ impl Config {
     }
 }
 
+#[derive(Debug, clap::Args)]
+struct ListArgs {
+    #[command(flatten)]
+    filter: FilterArgs,
+
+    #[arg(long, help = "print the catalog as JSON instead of text")]
+    json: bool,
+}
+
+#[derive(Debug, clap::Args)]
+struct CapturesArgs {
+    #[arg(
+        long,
+        default_value = ".",
+        help = "directory that captures were fetched into"
+    )]
+    dir: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+struct DnArgs {
+    #[command(flatten)]
+    captures: CapturesArgs,
+
+    #[arg(long, help = "print the matching numbers as JSON instead of text")]
+    json: bool,
+
+    #[arg(
+        long,
+        help = "find the numbers on this line equipment instead, e.g. \"0 2 03 12\""
+    )]
+    len: Option<String>,
+
+    #[arg(help = "directory numbers to look up, e.g. 555-1234; all of them if none are given")]
+    numbers: Vec<String>,
+}
+
+#[derive(Debug, clap::Args)]
+struct TgArgs {
+    #[command(flatten)]
+    captures: CapturesArgs,
+
+    #[arg(long, help = "print the groups as JSON instead of text")]
+    json: bool,
+
+    #[arg(help = "trunk group numbers to show, both TG and LTG; all of them if none are given")]
+    groups: Vec<String>,
+}
+
+#[derive(Debug, clap::Args)]
+struct HuntArgs {
+    #[command(flatten)]
+    captures: CapturesArgs,
+
+    #[arg(long, help = "print the groups as JSON instead of text")]
+    json: bool,
+
+    #[arg(help = "hunt or EBS group numbers to show; all of them if none are given")]
+    groups: Vec<String>,
+}
+
+#[derive(Debug, clap::Args)]
+struct InventoryArgs {
+    #[command(flatten)]
+    captures: CapturesArgs,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "text",
+        help = "how to print the inventory"
+    )]
+    format: inventory::Format,
+}
+
+#[derive(Debug, clap::Args)]
+struct OccupancyArgs {
+    #[command(flatten)]
+    captures: CapturesArgs,
+
+    #[arg(
+        long,
+        help = "number of circuits on each line pack, so that circuits the captures don't mention count as spare"
+    )]
+    circuits: Option<usize>,
+
+    #[arg(long, help = "print the report as JSON instead of text")]
+    json: bool,
+}
+
+#[derive(Debug, clap::Args)]
+struct NumberingArgs {
+    #[command(flatten)]
+    captures: CapturesArgs,
+
+    #[arg(long, help = "print the report as JSON instead of text")]
+    json: bool,
+}
+
+#[derive(Debug, clap::Args)]
+struct HtmlArgs {
+    #[command(flatten)]
+    captures: CapturesArgs,
+
+    #[arg(long, default_value = "html", help = "directory to write the site to")]
+    out: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+struct RebuildArgs {
+    #[command(flatten)]
+    captures: CapturesArgs,
+
+    #[arg(
+        long,
+        help = "directory to write one script per overlay to, numbered in the order to run them; printed to stdout if not given"
+    )]
+    out: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+struct ChangesArgs {
+    #[command(flatten)]
+    captures: CapturesArgs,
+
+    #[arg(help = "the capture to change, relative to --dir, e.g. DN/DN.txt")]
+    capture: String,
+
+    #[arg(
+        help = "the desired state: an export of the capture (.json, .yaml or .csv), edited as needed, or another capture (.txt)"
+    )]
+    desired: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+struct ApplyArgs {
+    #[command(flatten)]
+    login: LoginArgs,
+
+    #[arg(long, help = "run every step without asking first")]
+    yes: bool,
+
+    #[arg(
+        long,
+        help = "allow the script to change the configuration; without this, only que/quei requests can be sent"
+    )]
+    allow_writes: bool,
+
+    #[arg(
+        long,
+        default_value_t = 30,
+        help = "seconds without reaching an expected prompt before a step is considered rejected"
+    )]
+    stall_timeout: u64,
+
+    #[arg(
+        long,
+        conflicts_with = "allow_writes",
+        help = "don't re-fetch the changed overlays afterwards to check the result; writes always re-fetch them to generate the rollback script"
+    )]
+    no_verify: bool,
+
+    #[arg(
+        long,
+        help = "where to back up the overlays before a change, and to write the script that undoes it [default: the script's path with a .backup extension]"
+    )]
+    backup_dir: Option<PathBuf>,
+
+    #[arg(help = "the script to run, e.g. one written by `changes` or `rebuild`")]
+    script: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+struct TranslateArgs {
+    #[command(flatten)]
+    captures: CapturesArgs,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "active",
+        help = "which set of translations to use"
+    )]
+    set: translations::Set,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "prfx",
+        help = "the table to start in"
+    )]
+    table: translations::Table,
+
+    #[arg(
+        long,
+        help = "the translator number to start in, e.g. the line's prefix translator"
+    )]
+    translator: String,
+
+    #[arg(long, help = "print the trace as JSON instead of text")]
+    json: bool,
+
+    #[arg(help = "the dialed digits")]
+    digits: String,
+}
+
+#[derive(Debug, clap::Args)]
+#[group(id = "origin", required = true, multiple = false)]
+struct OriginArgs {
+    #[arg(long, group = "origin", help = "the originating directory number")]
+    dn: Option<String>,
+
+    #[arg(long, group = "origin", help = "the originating trunk group number")]
+    tg: Option<String>,
+
+    #[arg(
+        long,
+        group = "origin",
+        help = "start directly in this prefix translator"
+    )]
+    prfx: Option<String>,
+}
+
+impl From<OriginArgs> for Origin {
+    fn from(args: OriginArgs) -> Self {
+        match (args.dn, args.tg, args.prfx) {
+            (Some(dn), _, _) => Origin::Dn(dn),
+            (_, Some(tg), _) => Origin::TrunkGroup(tg),
+            (_, _, Some(prfx)) => Origin::PrefixTranslator(prfx),
+            (None, None, None) => unreachable!("clap requires one of --dn, --tg or --prfx"),
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+struct RouteArgs {
+    #[command(flatten)]
+    captures: CapturesArgs,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "active",
+        help = "which set of translations to use"
+    )]
+    set: translations::Set,
+
+    #[command(flatten)]
+    origin: OriginArgs,
+
+    #[arg(long, help = "print the simulation as JSON instead of text")]
+    json: bool,
+
+    #[arg(help = "the dialed digits")]
+    digits: String,
+}
+
+#[derive(Debug, clap::Args)]
+struct TrnsDiffArgs {
+    #[command(flatten)]
+    captures: CapturesArgs,
+
+    #[arg(long, help = "print the differences as JSON instead of text")]
+    json: bool,
+}
+
+#[derive(Debug, clap::Args)]
+struct DiffArgs {
+    #[arg(
+        long,
+        default_value = ".",
+        help = "git repository to read revisions from, when OLD or NEW is not a directory"
+    )]
+    repo: PathBuf,
+
+    #[arg(long, help = "print the differences as JSON instead of text")]
+    json: bool,
+
+    #[arg(help = "the old captures: a directory or a git revision, e.g. HEAD~1")]
+    old: String,
+
+    #[arg(help = "the new captures: a directory or a git revision")]
+    new: String,
+}
+
+#[derive(Debug, clap::Args)]
+struct LintArgs {
+    #[command(flatten)]
+    captures: CapturesArgs,
+
+    #[arg(long, help = "print the findings as JSON instead of text")]
+    json: bool,
+}
+
+#[derive(Debug, clap::Args)]
+struct ExportArgs {
+    #[arg(
+        long,
+        value_delimiter = ',',
+        required = true,
+        help = "formats to write, e.g. json,yaml"
+    )]
+    format: Vec<export::Format>,
+
+    #[arg(required = true, help = "captures to convert, e.g. NET/DSLK.txt")]
+    files: Vec<String>,
+}
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> anyhow::Result<ExitCode> {
     env_logger::builder()
         .filter_level(log::LevelFilter::Info)
         .parse_default_env()
         .init();
 
-    let config = Config::parse().read_password();
+    let config = Config::parse();
     debug!("parsed configuration: {:?}", config);
 
-    let mut console = Console::new(&config.hostname).await?;
+    match config.command {
+        Command::Fetch(args) => fetch(args).await,
+        Command::Apply(args) => apply_script(args).await,
+        Command::List(args) => {
+            list::print(&args.filter.select()?, args.json)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Export(args) => {
+            for file in &args.files {
+                let capture = Capture::from_file(file)?;
+                for format in &args.format {
+                    let path = format.write(&capture, file)?;
+                    info!("wrote {}", path.display());
+                }
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Dn(args) => lookup_dns(args),
+        Command::Tg(args) => show_trunk_groups(args),
+        Command::Hunt(args) => {
+            let numbers = DirectoryNumbers::load(&args.captures.dir)?;
+            let hunt = HuntGroups::load(&args.captures.dir)?;
+
+            let mut resolved = hunt.resolve(&numbers);
+            if !args.groups.is_empty() {
+                for number in &args.groups {
+                    if hunt.groups.iter().all(|g| &g.number != number) {
+                        let captures: Vec<&str> =
+                            hunt::Kind::ALL.iter().map(|k| k.capture()).collect();
+                        error!(
+                            "hunt group {} is not in any of {}",
+                            number,
+                            captures.join(", ")
+                        );
+                        return Ok(ExitCode::FAILURE);
+                    }
+                }
+                resolved.retain(|r| args.groups.contains(&r.group.number));
+            }
+
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&resolved)?);
+            } else {
+                for group in &resolved {
+                    println!("{}", group);
+                }
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Inventory(args) => {
+            let inventory = Inventory::load(&args.captures.dir)?;
+            match args.format {
+                inventory::Format::Text => print!("{}", inventory),
+                inventory::Format::Html => print!("{}", inventory.to_html()),
+                inventory::Format::Json => {
+                    println!("{}", serde_json::to_string_pretty(&inventory)?)
+                }
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Occupancy(args) => {
+            let inventory = Inventory::load(&args.captures.dir)?;
+            let numbers = DirectoryNumbers::load(&args.captures.dir)?;
+            let occupancy = Occupancy::new(&inventory, &numbers, args.circuits);
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&occupancy)?);
+            } else {
+                print!("{}", occupancy);
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Numbering(args) => {
+            let plan = NumberingPlan::load(&args.captures.dir)?;
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&plan)?);
+            } else {
+                print!("{}", plan);
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Html(args) => {
+            let pages = Site::load(&args.captures.dir)?.write(&args.out)?;
+            info!("wrote {} pages to {}", pages, args.out.display());
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Rebuild(args) => {
+            let scripts = rebuild::scripts(&args.captures.dir)?;
+            let Some(out) = &args.out else {
+                for (_, script) in &scripts {
+                    println!("{}", script);
+                }
+                return Ok(ExitCode::SUCCESS);
+            };
+
+            std::fs::create_dir_all(out).with_context(|| format!("creating {}", out.display()))?;
+            for (i, (ovly, script)) in scripts.iter().enumerate() {
+                let path = out.join(format!("{:02}-{}.dmo", i + 1, ovly));
+                std::fs::write(&path, script.to_string())
+                    .with_context(|| format!("writing {}", path.display()))?;
+                info!("wrote {}", path.display());
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Changes(args) => {
+            let current = Capture::load(&args.captures.dir, &args.capture)?
+                .with_context(|| format!("{} has not been captured", args.capture))?;
+            let desired = export::read(&args.desired)?;
+            let script = changes::script(&args.capture, &current, &desired);
+            if script.items.is_empty() {
+                info!("{} is already in the desired state", args.capture);
+            }
+            print!("{}", script);
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Route(args) => {
+            let dir = &args.captures.dir;
+            let numbers = DirectoryNumbers::load(dir)?;
+            let trunks = Trunks::load(dir)?;
+            let translations = Translations::load(dir, args.set)?;
+            let routing = Routing::load(dir)?;
+
+            let simulation = Simulation::run(
+                args.origin.into(),
+                &args.digits,
+                &numbers,
+                &trunks,
+                &translations,
+                &routing,
+            )?;
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&simulation)?);
+            } else {
+                print!("{}", simulation);
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::TrnsDiff(args) => compare_translation_sets(args),
+        Command::Lint(args) => {
+            let findings = lint::lint(&args.captures.dir)?;
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&findings)?);
+            } else {
+                for finding in &findings {
+                    println!("{}", finding);
+                }
+            }
+            Ok(if findings.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            })
+        }
+        Command::Diff(args) => {
+            let old = CaptureSet::new(&args.old, &args.repo);
+            let new = CaptureSet::new(&args.new, &args.repo);
+            let diff = SetDiff::new(&old, &new)?;
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&diff)?);
+            } else {
+                print!("{}", diff);
+            }
+            Ok(if diff.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            })
+        }
+        Command::Translate(args) => {
+            let translations = Translations::load(&args.captures.dir, args.set)?;
+            let trace = translations.translate(args.table, &args.translator, &args.digits);
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&trace)?);
+            } else {
+                print!("{}", trace);
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+/// Show what activating the inactive translations would change: everything that's in the inactive
+/// set but not the active one is "added".
+fn compare_translation_sets(args: TrnsDiffArgs) -> anyhow::Result<ExitCode> {
+    let dir = &args.captures.dir;
+    let mut diffs = vec![];
+    for table in translations::Table::ALL {
+        if !table.has_inactive_set() {
+            continue;
+        }
+        let active = translations::Set::Active.capture(table);
+        let inactive = translations::Set::Inactive.capture(table);
+        match (Capture::load(dir, &active)?, Capture::load(dir, &inactive)?) {
+            (Some(active_capture), Some(inactive_capture)) => diffs.push((
+                table,
+                CaptureDiff::for_file(&active, &active_capture, &inactive_capture),
+            )),
+            (None, None) => {}
+            (Some(_), None) => info!("{} was not captured; skipping {}", inactive, table),
+            (None, Some(_)) => info!("{} was not captured; skipping {}", active, table),
+        }
+    }
+
+    if args.json {
+        let diffs: std::collections::BTreeMap<_, _> =
+            diffs.into_iter().map(|(t, d)| (t.prompt(), d)).collect();
+        println!("{}", serde_json::to_string_pretty(&diffs)?);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for (table, diff) in &diffs {
+        if diff.is_empty() {
+            println!("{}: no differences", table);
+        } else {
+            println!("{}:", table);
+            print!("{}", diff);
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn show_trunk_groups(args: TgArgs) -> anyhow::Result<ExitCode> {
+    let trunks = Trunks::load(&args.captures.dir)?;
+
+    let mut resolved = trunks.resolve();
+    if !args.groups.is_empty() {
+        for number in &args.groups {
+            if trunk::Kind::ALL
+                .iter()
+                .all(|&kind| trunks.group(kind, number).is_none())
+            {
+                error!(
+                    "TG {} is not in any of {}",
+                    number,
+                    trunk::GROUP_CAPTURES.join(", ")
+                );
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        resolved.retain(|r| args.groups.contains(&r.group.number));
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&resolved)?);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for group in &resolved {
+        println!("{}", group);
+    }
+    if args.groups.is_empty() {
+        let unassigned: Vec<_> = trunks.unassigned().collect();
+        if !unassigned.is_empty() {
+            println!("Trunks not in any known group:");
+            for trunk in unassigned {
+                println!(
+                    "    {:3} {:>4}  {:12}  ({}:{})",
+                    trunk.kind,
+                    trunk.group.as_deref().unwrap_or("-"),
+                    trunk.equipment,
+                    trunk.source,
+                    trunk.line
+                );
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn lookup_dns(args: DnArgs) -> anyhow::Result<ExitCode> {
+    let numbers = DirectoryNumbers::load(&args.captures.dir)?;
+
+    let mut found = vec![];
+    let mut missing = false;
+    if let Some(len) = &args.len {
+        found.extend(numbers.on_len(len));
+    } else if args.numbers.is_empty() {
+        found.extend(numbers.iter());
+    }
+    for query in &args.numbers {
+        match numbers.find(query) {
+            Some(dn) => found.push(dn),
+            None => {
+                let candidates = numbers.candidates(query);
+                if candidates.is_empty() {
+                    error!("{} is not in any of {}", query, dn::CAPTURES.join(", "));
+                } else {
+                    let candidates: Vec<&str> = candidates.iter().map(|n| n.dn.as_str()).collect();
+                    error!("{} could be any of {}", query, candidates.join(", "));
+                }
+                missing = true;
+            }
+        }
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&found)?);
+    } else {
+        for dn in found {
+            println!("{}", dn);
+        }
+    }
+
+    Ok(if missing {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+async fn fetch(config: FetchArgs) -> anyhow::Result<ExitCode> {
+    // resolve the filters before asking for a password, so that typos are caught right away.
+    let fetchers = config.filter.select()?;
+
+    if config.dry_run {
+        list::print_plan(&fetchers, &config.export);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut config = config;
+    config.login = config.login.read_password();
+
+    let mut console = match connect(&config.login).await {
+        Ok(console) => console,
+        Err(e) => {
+            error!("could not log in to the DMS-10: {:#}", e);
+            return Ok(ExitCode::from(summary::EXIT_CONNECTION_FAILURE));
+        }
+    };
+    console.set_give_up_after(config.stall_timeout.map(Duration::from_secs));
+
+    let retry_policy = RetryPolicy {
+        retries: config.retries,
+        delay: Duration::from_secs(config.retry_delay),
+    };
+
+    let mut summary = Summary::default();
+    let mut stopped = false;
+    let mut disconnected = false;
+
+    'next_fetcher: for fetcher in fetchers {
+        'repeat_this_fetcher: loop {
+            if disconnected {
+                summary.skipped(fetcher.filename(), "the connection to the DMS-10 was lost");
+                continue 'next_fetcher;
+            }
+            if stopped {
+                summary.skipped(fetcher.filename(), "not attempted after an earlier failure");
+                continue 'next_fetcher;
+            }
+
+            let fetch_future = fetcher.fetch_and_write_with_retries(&mut console, retry_policy);
+            tokio::pin!(fetch_future);
+
+            'keep_waiting: loop {
+                let ctrl_c = tokio::signal::ctrl_c();
+
+                select! {
+                    r = &mut fetch_future => {
+                        let r = r
+                            .with_context(|| format!("fetch_and_write {}", fetcher.filename()))
+                            .and_then(|capture| {
+                                for format in &config.export {
+                                    format.write(&capture, fetcher.filename())?;
+                                }
+                                Ok(())
+                            });
+                        match r {
+                            Ok(()) => summary.ok(fetcher.filename()),
+                            Err(e) => {
+                                if console::is_disconnected(&e) {
+                                    disconnected = true;
+                                    summary.disconnected();
+                                }
+                                summary.failed(fetcher.filename(), e);
+                                stopped = !config.keep_going;
+                            }
+                        }
+                        continue 'next_fetcher;
+                    }
+                    _ = ctrl_c => {
+                        // TODO: this will potentially process data that has been buffered during a
+                        // long-running fetch, if the user accidentally typed something on their
+                        // keyboard.  Ideally we could clear the stdin buffer before doing this, but
+                        // ... there is no try_read_all() or something that will read up until it
+                        // *blocks* rather than EOF.
+                        let stdin = std::io::stdin();
+                        loop {
+                            eprintln!("Ctrl-C detected.  Say 'w' to keep waiting, 'r' to repeat this OVLY and TYP, or 'n' to skip to the next.");
+
+                            let mut buf = String::new();
+                            stdin.read_line(&mut buf).context("reading from stdin failed")?;
+                            match buf.as_str().trim_ascii_end() {
+                                "w" => continue 'keep_waiting, // TODO: this seems not to work...?
+                                "r" => continue 'repeat_this_fetcher,
+                                "n" => {
+                                    summary.skipped(fetcher.filename(), "skipped with Ctrl-C");
+                                    continue 'next_fetcher;
+                                }
+                                _ => eprintln!("That was not one of the options, try again."),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    summary.print();
+
+    Ok(summary.exit_code())
+}
+
+/// Run a script against the DMS-10, stopping at the first step that fails, and then fetch every
+/// overlay that the script entered so that the result can be compared with the captures from
+/// before.  Captures are read and written in the current directory, like `fetch`.
+///
+/// When writes are allowed, those overlays are first fetched fresh and backed up, and afterwards a
+/// `rollback.dmo` that undoes whatever the script did is written next to the backup.
+async fn apply_script(args: ApplyArgs) -> anyhow::Result<ExitCode> {
+    let script = Script::from_file(&args.script)?;
+    let overlays = script.overlays();
+    let mut fetchers: Vec<Fetcher> = all_fetchers()
+        .into_iter()
+        .filter(|f| overlays.iter().any(|o| o == f.ovly()))
+        .collect();
+    // in the order the script changes them, so that the rollback can undo them in reverse
+    fetchers.sort_by_key(|f| overlays.iter().position(|o| o == f.ovly()));
+    let mut before = fetchers
+        .iter()
+        .map(|f| Capture::load(".", f.filename()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let backup_dir = args
+        .backup_dir
+        .clone()
+        .unwrap_or_else(|| args.script.with_extension("backup"));
+
+    let mut args = args;
+    args.login = args.login.read_password();
+    let mut console = match connect(&args.login).await {
+        Ok(console) => console,
+        Err(e) => {
+            error!("could not log in to the DMS-10: {:#}", e);
+            return Ok(ExitCode::from(summary::EXIT_CONNECTION_FAILURE));
+        }
+    };
+    console.set_give_up_after(Some(Duration::from_secs(args.stall_timeout)));
+    let retry_policy = RetryPolicy {
+        retries: 1,
+        delay: Duration::from_secs(5),
+    };
+    if args.allow_writes {
+        match apply::backup(
+            &mut console,
+            &fetchers,
+            retry_policy,
+            &backup_dir,
+            &args.script,
+        )
+        .await
+        {
+            Ok(captures) => before = captures.into_iter().map(Some).collect(),
+            Err(e) => {
+                error!("not changing anything, since the backup failed: {:#}", e);
+                return Ok(ExitCode::from(summary::EXIT_PARTIAL_FAILURE));
+            }
+        }
+        console.set_access(Access::ReadWrite);
+    }
+
+    let mut exit_code = ExitCode::SUCCESS;
+    match apply::run(&mut console, &script, args.yes).await {
+        Ok(apply::Outcome::Finished) => info!("ran every step of {}", args.script.display()),
+        Ok(apply::Outcome::Stopped(step)) => {
+            info!("stopped before step {}", step);
+            exit_code = ExitCode::from(summary::EXIT_PARTIAL_FAILURE);
+        }
+        Err(e) => {
+            error!("{:#}", e);
+            exit_code = ExitCode::from(summary::EXIT_PARTIAL_FAILURE);
+        }
+    }
+
+    console.set_access(Access::ReadOnly);
+    if args.no_verify {
+        return Ok(exit_code);
+    }
+
+    // Get out of whatever prompt the script left us at before fetching anything.  If even that
+    // doesn't work, log in again: the rollback can't be written without fetching the result.
+    let reset = async {
+        console.send(b"****\n").await?;
+        console.run_until_human_prompt(HASH).await
+    };
+    let mut console = match reset.await {
+        Ok(_) => Some(console),
+        Err(e) => {
+            error!(
+                "could not get back to the # prompt, logging in again: {:#}",
+                e
+            );
+            connect(&args.login)
+                .await
+                .inspect_err(|e| error!("could not log in to the DMS-10 again: {:#}", e))
+                .ok()
+        }
+    };
+
+    let mut after = vec![];
+    for (fetcher, before) in fetchers.iter().zip(&before) {
+        let Some(console) = &mut console else {
+            exit_code = ExitCode::from(summary::EXIT_PARTIAL_FAILURE);
+            after.push(None);
+            continue;
+        };
+        let capture = match fetcher
+            .fetch_and_write_with_retries(console, retry_policy)
+            .await
+        {
+            Ok(capture) => capture,
+            Err(e) => {
+                error!("could not re-fetch {}: {:#}", fetcher.filename(), e);
+                exit_code = ExitCode::from(summary::EXIT_PARTIAL_FAILURE);
+                after.push(None);
+                continue;
+            }
+        };
+        match before {
+            None => info!("{} had not been captured before", fetcher.filename()),
+            Some(before) => {
+                let diff = CaptureDiff::for_file(fetcher.filename(), before, &capture);
+                if diff.is_empty() {
+                    info!("{}: unchanged", fetcher.filename());
+                } else {
+                    println!("{}:", fetcher.filename());
+                    print!("{}", diff);
+                }
+            }
+        }
+        after.push(Some(capture));
+    }
+
+    if args.allow_writes {
+        let captures: Vec<(&str, &Capture, Option<&Capture>)> = fetchers
+            .iter()
+            .zip(&before)
+            .zip(&after)
+            .filter_map(|((fetcher, before), after)| {
+                Some((fetcher.filename(), before.as_ref()?, after.as_ref()))
+            })
+            .collect();
+        let mut rollback = Script::default();
+        rollback.comment(format!(
+            "undoes {}, back to the captures in {}",
+            args.script.display(),
+            backup_dir.display()
+        ));
+        rollback.extend(apply::rollback(&captures, &backup_dir));
+
+        let path = backup_dir.join("rollback.dmo");
+        std::fs::write(&path, rollback.to_string())
+            .with_context(|| format!("writing {}", path.display()))?;
+        info!(
+            "wrote {}; to undo this change, run `apply --allow-writes {}`",
+            path.display(),
+            path.display()
+        );
+    }
+
+    Ok(exit_code)
+}
+
+/// Spawn telnet and walk through both the Unix login and the DMS-10 `LOGI`, leaving the console
+/// sitting at the `  # ` prompt.
+async fn connect(login: &LoginArgs) -> anyhow::Result<Console> {
+    let mut console = Console::new(&login.hostname).await?;
     info!("connected to DMS-10!");
 
     console.run_until_human_prompt("user: ").await?;
@@ -58,7 +1079,7 @@ async fn main() -> anyhow::Result<()> {
 
     console.run_until_human_prompt("password: ").await?;
 
-    let mut password_buffer = config.password;
+    let mut password_buffer = login.password.clone();
     password_buffer.push('\n');
     console
         .send(password_buffer.as_bytes())
@@ -86,6 +1107,11 @@ async fn main() -> anyhow::Result<()> {
         .context("sending password (DMS-10)")?;
     console.run_until_human_prompt(HASH).await?;
 
+    Ok(console)
+}
+
+/// Every resource that this tool knows how to fetch, sorted by filename.
+fn all_fetchers() -> Vec<Fetcher> {
     let common: &[(&str, &[&str])] = &[
         ("alrm", &["alpt"]),
         ("area", &["hnpa", "rc"]),
@@ -141,50 +1167,5 @@ async fn main() -> anyhow::Result<()> {
 
     fetchers.sort_unstable_by(|x, y| x.filename().cmp(y.filename()));
 
-    let files: HashSet<String> = config.files.into_iter().collect();
-
-    'next_fetcher: for fetcher in fetchers {
-        'repeat_this_fetcher: loop {
-            if !files.is_empty() && !files.contains(fetcher.filename()) {
-                // the user passed in a filter list, and this fetcher is not in it.  skip it entirely.
-                continue 'next_fetcher;
-            }
-
-            let fetch_future = fetcher.fetch_and_write(&mut console);
-            tokio::pin!(fetch_future);
-
-            'keep_waiting: loop {
-                let ctrl_c = tokio::signal::ctrl_c();
-
-                select! {
-                    r = &mut fetch_future => {
-                        r.with_context(|| format!("fetch_and_write {}", fetcher.filename()))?;
-                        continue 'next_fetcher;
-                    }
-                    _ = ctrl_c => {
-                        // TODO: this will potentially process data that has been buffered during a
-                        // long-running fetch, if the user accidentally typed something on their
-                        // keyboard.  Ideally we could clear the stdin buffer before doing this, but
-                        // ... there is no try_read_all() or something that will read up until it
-                        // *blocks* rather than EOF.
-                        let stdin = std::io::stdin();
-                        loop {
-                            eprintln!("Ctrl-C detected.  Say 'w' to keep waiting, 'r' to repeat this OVLY and TYP, or 'n' to skip to the next.");
-
-                            let mut buf = String::new();
-                            stdin.read_line(&mut buf).context("reading from stdin failed")?;
-                            match buf.as_str().trim_ascii_end() {
-                                "w" => continue 'keep_waiting, // TODO: this seems not to work...?
-                                "r" => continue 'repeat_this_fetcher,
-                                "n" => continue 'next_fetcher,
-                                _ => eprintln!("That was not one of the options, try again."),
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(())
+    fetchers
 }
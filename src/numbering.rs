@@ -0,0 +1,214 @@
+//! Which line numbers are in use in each office code (NXX), from the DN captures and the home NPA
+//! and rate center captures in the `AREA` overlay.
+//!
+//! A number counts as assigned if it has line equipment, and as reserved if the DN captures list it
+//! without any (e.g. a hunt group pilot or a number held on intercept).  Everything else in
+//! `0000`–`9999` is unassigned.
+
+use std::{collections::BTreeMap, fmt, path::Path};
+
+use serde::{Serialize, Serializer};
+
+use crate::{dn::DirectoryNumbers, parser::Capture};
+
+pub const AREA_CAPTURES: &[&str] = &["AREA/HNPA.txt", "AREA/RC.txt"];
+
+// The prompts for each attribute, in order of preference.
+const NPA: &[&str] = &["HNPA", "NPA"];
+const OFFICE_CODE: &[&str] = &["NXX", "OFC", "OFCD", "CODE"];
+
+const LINE_NUMBERS: usize = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Status {
+    Unassigned,
+    Reserved,
+    Assigned,
+}
+
+/// A run of consecutive line numbers, printed like `0100-0199`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Range {
+    pub first: usize,
+    pub last: usize,
+}
+
+impl Range {
+    /// How many line numbers the range covers.
+    pub fn count(&self) -> usize {
+        self.last - self.first + 1
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.first == self.last {
+            write!(f, "{:04}", self.first)
+        } else {
+            write!(f, "{:04}-{:04}", self.first, self.last)
+        }
+    }
+}
+
+impl Serialize for Range {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OfficeCode {
+    pub code: String,
+    pub assigned: Vec<Range>,
+    pub reserved: Vec<Range>,
+    pub unassigned: Vec<Range>,
+}
+
+impl OfficeCode {
+    fn new(code: String, statuses: &[Status]) -> Self {
+        Self {
+            code,
+            assigned: ranges(statuses, Status::Assigned),
+            reserved: ranges(statuses, Status::Reserved),
+            unassigned: ranges(statuses, Status::Unassigned),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct NumberingPlan {
+    /// The home NPAs, if `AREA/HNPA.txt` was captured.
+    pub npas: Vec<String>,
+    pub office_codes: Vec<OfficeCode>,
+    /// Numbers whose office code can't be told, because they are too short and there is more than
+    /// one office code to choose from.
+    pub unknown: Vec<String>,
+}
+
+impl NumberingPlan {
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        let numbers = DirectoryNumbers::load(dir)?;
+
+        let mut plan = NumberingPlan::default();
+        let mut codes: BTreeMap<String, Vec<Status>> = BTreeMap::new();
+        for &file in AREA_CAPTURES {
+            let Some(capture) = Capture::load(dir, file)? else {
+                continue;
+            };
+            for record in &capture.records {
+                for npa in record.get_any(NPA).unwrap_or_default() {
+                    if !plan.npas.contains(npa) {
+                        plan.npas.push(npa.clone());
+                    }
+                }
+                for code in record.get_any(OFFICE_CODE).unwrap_or_default() {
+                    codes
+                        .entry(code.clone())
+                        .or_insert_with(|| vec![Status::Unassigned; LINE_NUMBERS]);
+                }
+            }
+        }
+
+        // a number printed without its office code can only be in the office's one code, if it has
+        // just one
+        let only_code = match codes.len() {
+            1 => codes.keys().next().cloned(),
+            _ => None,
+        };
+        for dn in numbers.iter() {
+            let digits = dn.digits();
+            let (code, line) = match (digits.len(), &only_code) {
+                (n, _) if n >= 7 => (digits[n - 7..n - 4].to_owned(), &digits[n - 4..]),
+                (4, Some(code)) => (code.clone(), &digits[..]),
+                _ => {
+                    plan.unknown.push(dn.dn.clone());
+                    continue;
+                }
+            };
+            let Ok(line) = line.parse::<usize>() else {
+                plan.unknown.push(dn.dn.clone());
+                continue;
+            };
+
+            let status = if dn.len.is_some() {
+                Status::Assigned
+            } else {
+                Status::Reserved
+            };
+            let statuses = codes
+                .entry(code)
+                .or_insert_with(|| vec![Status::Unassigned; LINE_NUMBERS]);
+            if statuses[line] != Status::Assigned {
+                statuses[line] = status;
+            }
+        }
+
+        plan.office_codes = codes
+            .into_iter()
+            .map(|(code, statuses)| OfficeCode::new(code, &statuses))
+            .collect();
+        Ok(plan)
+    }
+}
+
+impl fmt::Display for NumberingPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.npas.is_empty() {
+            writeln!(f, "home NPA {}", self.npas.join(", "))?;
+            writeln!(f)?;
+        }
+        for code in &self.office_codes {
+            writeln!(
+                f,
+                "NXX {}: {} assigned, {} reserved, {} unassigned",
+                code.code,
+                count(&code.assigned),
+                count(&code.reserved),
+                count(&code.unassigned)
+            )?;
+            writeln!(f, "    assigned    {}", list(&code.assigned))?;
+            writeln!(f, "    reserved    {}", list(&code.reserved))?;
+            writeln!(f, "    unassigned  {}", list(&code.unassigned))?;
+        }
+        if !self.unknown.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "office code unknown: {}", self.unknown.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Compress every line number with `status` into runs.
+fn ranges(statuses: &[Status], status: Status) -> Vec<Range> {
+    let mut ranges: Vec<Range> = vec![];
+    for (number, s) in statuses.iter().enumerate() {
+        if *s != status {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(range) if range.last + 1 == number => range.last = number,
+            _ => ranges.push(Range {
+                first: number,
+                last: number,
+            }),
+        }
+    }
+    ranges
+}
+
+fn count(ranges: &[Range]) -> usize {
+    ranges.iter().map(Range::count).sum()
+}
+
+fn list(ranges: &[Range]) -> String {
+    if ranges.is_empty() {
+        "(none)".to_owned()
+    } else {
+        ranges
+            .iter()
+            .map(Range::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
@@ -0,0 +1,191 @@
+//! Which circuits on each line pack are wired to a directory number, and which are spare.
+//!
+//! The circuits on a pack are whatever the `CPK` captures list under it plus whatever line
+//! equipment the DN captures put on it.  Neither says how many circuits a pack actually has, so
+//! that can be given explicitly to count the circuits nothing mentions as spare too.
+
+use std::{collections::BTreeMap, fmt};
+
+use serde::Serialize;
+
+use crate::{
+    dn::{DirectoryNumber, DirectoryNumbers},
+    inventory::{self, Inventory, Pack},
+};
+
+/// Pack types that hold line circuits.  Any other pack that a DN's line equipment is on counts as
+/// a line pack too.
+pub const LINE_PACK_TYPES: &[&str] = &["LPK", "SLC", "SLPK"];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CircuitUse<'a> {
+    pub location: String,
+    /// The numbers on this circuit; more than one for a party line, none if it's spare.
+    pub numbers: Vec<&'a DirectoryNumber>,
+}
+
+impl CircuitUse<'_> {
+    pub fn is_spare(&self) -> bool {
+        self.numbers.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PackOccupancy<'a> {
+    pub location: &'a str,
+    pub pack_type: Option<&'a str>,
+    pub circuits: Vec<CircuitUse<'a>>,
+}
+
+impl PackOccupancy<'_> {
+    pub fn assigned(&self) -> usize {
+        self.circuits.iter().filter(|c| !c.is_spare()).count()
+    }
+
+    pub fn spare(&self) -> usize {
+        self.circuits.len() - self.assigned()
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Occupancy<'a> {
+    pub packs: Vec<PackOccupancy<'a>>,
+    /// Numbers whose line equipment isn't on any pack in the captures.
+    pub unplaced: Vec<&'a DirectoryNumber>,
+}
+
+impl<'a> Occupancy<'a> {
+    /// Work out the occupancy of every line pack.  If `circuits_per_pack` is given, circuits
+    /// `0` to `circuits_per_pack - 1` are assumed to exist on every line pack, in addition to any
+    /// that the captures mention.
+    pub fn new(
+        inventory: &'a Inventory,
+        numbers: &'a DirectoryNumbers,
+        circuits_per_pack: Option<usize>,
+    ) -> Self {
+        let mut occupancy = Occupancy::default();
+
+        // every circuit the DN captures use, by the pack it's on
+        let mut used: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for dn in numbers.iter() {
+            let Some(len) = &dn.len else {
+                continue;
+            };
+            match inventory.pack_for(len) {
+                Some(pack) => used
+                    .entry(pack.location.as_str())
+                    .or_default()
+                    .push(normalize(len)),
+                None => occupancy.unplaced.push(dn),
+            }
+        }
+
+        for pack in inventory.packs() {
+            let used = used.remove(pack.location.as_str()).unwrap_or_default();
+            if used.is_empty() && !is_line_pack(pack) {
+                continue;
+            }
+
+            let mut locations: Vec<String> = pack
+                .circuits
+                .iter()
+                .map(|c| normalize(&c.location))
+                .collect();
+            locations.extend(used);
+            if let Some(count) = circuits_per_pack {
+                // padded like the circuits the captures print, e.g. `05` rather than `5`
+                let width = locations
+                    .iter()
+                    .filter_map(|l| l.split_whitespace().last())
+                    .map(str::len)
+                    .max()
+                    .unwrap_or(0);
+                locations.extend(
+                    (0..count).map(|i| format!("{} {:0width$}", pack.location, i, width = width)),
+                );
+            }
+            // the same circuit spelled differently is one circuit, and the sort is stable, so the
+            // spelling from the captures (which come first) is the one that's kept
+            locations.sort_by_key(|l| inventory::sort_key(&inventory::location_key(l)));
+            locations.dedup_by_key(|l| inventory::location_key(l));
+
+            occupancy.packs.push(PackOccupancy {
+                location: &pack.location,
+                pack_type: pack.pack_type.as_deref(),
+                circuits: locations
+                    .into_iter()
+                    .map(|location| CircuitUse {
+                        numbers: numbers.on_len(&location).collect(),
+                        location,
+                    })
+                    .collect(),
+            });
+        }
+        occupancy
+    }
+
+    pub fn circuits(&self) -> usize {
+        self.packs.iter().map(|p| p.circuits.len()).sum()
+    }
+
+    pub fn assigned(&self) -> usize {
+        self.packs.iter().map(PackOccupancy::assigned).sum()
+    }
+}
+
+impl fmt::Display for Occupancy<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for pack in &self.packs {
+            writeln!(
+                f,
+                "{:14}  {:6}  {}/{} assigned ({}%), {} spare",
+                pack.location,
+                pack.pack_type.unwrap_or("?"),
+                pack.assigned(),
+                pack.circuits.len(),
+                percent(pack.assigned(), pack.circuits.len()),
+                pack.spare()
+            )?;
+            for circuit in &pack.circuits {
+                let numbers: Vec<&str> = circuit.numbers.iter().map(|n| n.dn.as_str()).collect();
+                if numbers.is_empty() {
+                    writeln!(f, "    {:16}  spare", circuit.location)?;
+                } else {
+                    writeln!(f, "    {:16}  {}", circuit.location, numbers.join(", "))?;
+                }
+            }
+        }
+
+        if !self.unplaced.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "not on any pack in the captures:")?;
+            for dn in &self.unplaced {
+                writeln!(f, "    {:16}  {}", dn.len.as_deref().unwrap_or(""), dn.dn)?;
+            }
+        }
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "total: {}/{} circuits assigned ({}%) on {} line pack(s)",
+            self.assigned(),
+            self.circuits(),
+            percent(self.assigned(), self.circuits()),
+            self.packs.len()
+        )
+    }
+}
+
+fn is_line_pack(pack: &Pack) -> bool {
+    pack.pack_type
+        .as_deref()
+        .is_some_and(|t| LINE_PACK_TYPES.contains(&t))
+}
+
+fn percent(part: usize, whole: usize) -> usize {
+    (part * 100).checked_div(whole).unwrap_or(0)
+}
+
+fn normalize(location: &str) -> String {
+    location.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,418 @@
+//! Turn the text captured from a DMO `QUE` (or `QUEI`) request into structured records.
+//!
+//! Every line of interest that the DMS-10 prints is laid out as a prompt and its value:
+//!
+//! ```text
+//!     PACK  0 0 1 2
+//!     PTYP  LPK
+//!     OPT   ABC DEF
+//!           GHI
+//! ```
+//!
+//! where a line with no prompt continues the value of the previous one.  Entities are separated by
+//! blank lines, and are preceded by the echoed `REQ`, `TYP` and selection prompts that asked for
+//! them.
+
+use std::{fmt, path::Path};
+
+use anyhow::Context;
+use serde::{
+    de::{MapAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// Everything that was printed in response to one request.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capture {
+    /// The `REQ`, `TYP` (and for some overlays `CLI`) prompts and the selection prompt that was
+    /// answered with `all`, in the order they were asked.
+    #[serde(default)]
+    pub header: Record,
+    pub records: Vec<Record>,
+}
+
+/// One entity, e.g. a single pack or directory number.  Prompts are kept in the order the DMS-10
+/// printed them; a prompt that appears more than once, or whose value continues onto following
+/// lines, collects all of its values in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    fields: Vec<Field>,
+    line: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Field {
+    pub prompt: String,
+    pub values: Vec<String>,
+}
+
+impl Capture {
+    /// Parse the contents of a capture, e.g. one written by `fetch`.
+    pub fn parse(text: &str) -> Self {
+        let mut lines: Vec<(usize, Line)> = text
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| Line::parse(line).map(|l| (i + 1, l)))
+            .collect();
+
+        let mut capture = Capture::default();
+
+        // Everything up to the (last) echoed REQ is the banner, e.g. `DMO000    CPK`.
+        if let Some(req) = lines
+            .iter()
+            .rposition(|(_, l)| matches!(l, Line::Field(p, v) if p == "REQ" && !v.is_empty()))
+        {
+            lines.drain(..req);
+            capture.header.line = lines[0].0;
+
+            // CLI is only asked in the CLI overlay, so each of these is taken only if present.
+            for expected in ["REQ", "TYP", "CLI"] {
+                if let Some((_, Line::Field(prompt, value))) = lines.first() {
+                    if prompt == expected {
+                        capture.header.push(prompt, value);
+                        lines.remove(0);
+                    }
+                }
+            }
+
+            // the selection prompt, e.g. `PACK  all`, is absent for overlays that don't ask one.
+            if let Some((_, Line::Field(prompt, value))) = lines.first() {
+                if value == "all" {
+                    capture.header.push(prompt, value);
+                    lines.remove(0);
+                }
+            }
+        }
+
+        // the REQ prompt that is printed once the request has finished
+        if let Some((_, Line::Field(prompt, value))) = lines.last() {
+            if prompt == "REQ" && value.is_empty() {
+                lines.pop();
+            }
+        }
+
+        let mut current = Record::default();
+        for (line, item) in lines {
+            match item {
+                Line::Blank => capture.finish(&mut current),
+                Line::Continuation(value) => current.continue_last(&value),
+                Line::Field(prompt, value) => {
+                    if current.fields.first().is_some_and(|f| f.prompt == prompt) {
+                        // the key prompt came around again without a blank line in between
+                        capture.finish(&mut current);
+                    }
+                    if current.fields.is_empty() {
+                        current.line = line;
+                    }
+                    current.push(&prompt, &value);
+                }
+            }
+        }
+        capture.finish(&mut current);
+
+        capture
+    }
+
+    /// Every prompt that appears in any record, in the order the DMS-10 prints them.  A prompt that
+    /// only some records have is placed right after the prompt that preceded it in the first
+    /// record that has it, so the order is stable no matter which entities are present.
+    pub fn prompts(&self) -> Vec<&str> {
+        let mut prompts: Vec<&str> = vec![];
+        for record in &self.records {
+            let mut previous: Option<usize> = None;
+            for field in &record.fields {
+                let index = match prompts.iter().position(|&p| p == field.prompt) {
+                    Some(index) => index,
+                    None => {
+                        let index = previous.map_or(prompts.len(), |p| p + 1);
+                        prompts.insert(index, &field.prompt);
+                        index
+                    }
+                };
+                previous = Some(index);
+            }
+        }
+        prompts
+    }
+
+    /// Read and parse a capture that was previously written to disk.
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        Ok(Self::parse(&String::from_utf8_lossy(&bytes)))
+    }
+
+    /// Read and parse `filename` (e.g. `DN/DN.txt`) from a directory of captures.  A capture that
+    /// was never fetched is `None` rather than an error, since most captures are optional.
+    pub fn load(dir: impl AsRef<Path>, filename: &str) -> anyhow::Result<Option<Self>> {
+        let path = dir.as_ref().join(filename);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Self::from_file(path).map(Some)
+    }
+
+    fn finish(&mut self, current: &mut Record) {
+        if !current.fields.is_empty() {
+            self.records.push(std::mem::take(current));
+        }
+    }
+}
+
+impl Record {
+    /// A record that wasn't read from a capture, e.g. one written by hand.  Its line number is 0.
+    pub fn from_fields(fields: Vec<Field>) -> Self {
+        Self { fields, line: 0 }
+    }
+
+    /// All of the prompts in this record, in the order they were printed.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// All of the values printed for `prompt`, or `None` if the prompt does not appear at all.
+    pub fn get(&self, prompt: &str) -> Option<&[String]> {
+        self.fields
+            .iter()
+            .find(|f| f.prompt == prompt)
+            .map(|f| f.values.as_slice())
+    }
+
+    /// The first value printed for `prompt`, which for most prompts is the only one.
+    pub fn first(&self, prompt: &str) -> Option<&str> {
+        self.get(prompt)
+            .and_then(|values| values.first())
+            .map(String::as_str)
+    }
+
+    /// Like `get`, but for a prompt that is spelled differently depending on the overlay or software
+    /// generic.  The first of `prompts` that is present wins.
+    pub fn get_any(&self, prompts: &[&str]) -> Option<&[String]> {
+        prompts.iter().find_map(|prompt| self.get(prompt))
+    }
+
+    /// Like `first`, but for a prompt with several possible spellings; see `get_any`.
+    pub fn first_any(&self, prompts: &[&str]) -> Option<&str> {
+        prompts.iter().find_map(|prompt| self.first(prompt))
+    }
+
+    /// The first prompt and its value, which identifies the entity (e.g. `PACK  0 0 1 2`).
+    pub fn key(&self) -> Option<(&str, &str)> {
+        let field = self.fields.first()?;
+        Some((
+            &field.prompt,
+            field.values.first().map(String::as_str).unwrap_or(""),
+        ))
+    }
+
+    /// The 1-based line number in the capture where this record starts.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    fn push(&mut self, prompt: &str, value: &str) {
+        let index = match self.fields.iter().position(|f| f.prompt == prompt) {
+            Some(index) => index,
+            None => {
+                self.fields.push(Field {
+                    prompt: prompt.to_owned(),
+                    values: vec![],
+                });
+                self.fields.len() - 1
+            }
+        };
+        if !value.is_empty() {
+            self.fields[index].values.push(value.to_owned());
+        }
+    }
+
+    fn continue_last(&mut self, value: &str) {
+        if let Some(last) = self.fields.last_mut() {
+            last.values.push(value.to_owned());
+        }
+    }
+}
+
+/// Records are serialized as a map from prompt to values, preserving the order they were printed.
+impl Serialize for Record {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.fields.len()))?;
+        for field in &self.fields {
+            map.serialize_entry(&field.prompt, &field.values)?;
+        }
+        map.end()
+    }
+}
+
+/// The inverse of `Serialize`, so that an exported capture (possibly edited by hand) can be read
+/// back.  A prompt may be given a single string instead of a list of values.
+impl<'de> Deserialize<'de> for Record {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Values {
+            One(String),
+            Many(Vec<String>),
+        }
+
+        struct RecordVisitor;
+
+        impl<'de> Visitor<'de> for RecordVisitor {
+            type Value = Record;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map from prompt to values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Record, A::Error> {
+                let mut fields = vec![];
+                while let Some((prompt, values)) = map.next_entry::<String, Values>()? {
+                    fields.push(Field {
+                        prompt,
+                        values: match values {
+                            Values::One(value) => vec![value],
+                            Values::Many(values) => values,
+                        },
+                    });
+                }
+                Ok(Record::from_fields(fields))
+            }
+        }
+
+        deserializer.deserialize_map(RecordVisitor)
+    }
+}
+
+/// Print the record back out in the same layout the DMS-10 uses.
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for field in &self.fields {
+            let mut values = field.values.iter();
+            writeln!(
+                f,
+                "    {:4}  {}",
+                field.prompt,
+                values.next().map(String::as_str).unwrap_or("")
+            )?;
+            for value in values {
+                writeln!(f, "          {}", value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+enum Line {
+    Blank,
+    Continuation(String),
+    Field(String, String),
+}
+
+impl Line {
+    /// Returns `None` for lines that aren't part of the DMO's prompt/value layout at all, like the
+    /// `  # ` prompt that the request was typed at.
+    fn parse(line: &str) -> Option<Self> {
+        if line.trim().is_empty() {
+            return Some(Line::Blank);
+        }
+
+        let rest = line.strip_prefix("    ")?;
+        if rest.starts_with(' ') {
+            return Some(Line::Continuation(rest.trim().to_owned()));
+        }
+
+        let (prompt, value) = rest.split_once(' ').unwrap_or((rest, ""));
+        Some(Line::Field(prompt.to_owned(), value.trim().to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKS: &str = "\
+DMO000    CPK
+
+  # ovly cpk
+    REQ   que
+    TYP   pack
+    PACK  all
+    PACK  0 0 1 2
+    PTYP  LPK
+    OPT   ABC DEF
+          GHI
+
+    PACK  0 0 1 3
+    PTYP  SLC
+    PACK  0 0 1 4
+    PTYP  DCM
+
+    REQ   
+";
+
+    #[test]
+    fn header_is_stripped() {
+        let capture = Capture::parse(PACKS);
+        let header: Vec<(&str, &[String])> = capture
+            .header
+            .fields()
+            .iter()
+            .map(|f| (f.prompt.as_str(), f.values.as_slice()))
+            .collect();
+        assert_eq!(
+            header,
+            [
+                ("REQ", &["que".to_owned()][..]),
+                ("TYP", &["pack".to_owned()][..]),
+                ("PACK", &["all".to_owned()][..]),
+            ]
+        );
+        assert_eq!(capture.header.line(), 4);
+        assert_eq!(capture.records[0].key(), Some(("PACK", "0 0 1 2")));
+    }
+
+    #[test]
+    fn continuation_lines_extend_the_previous_prompt() {
+        let capture = Capture::parse(PACKS);
+        assert_eq!(
+            capture.records[0].get("OPT"),
+            Some(&["ABC DEF".to_owned(), "GHI".to_owned()][..])
+        );
+        assert_eq!(capture.records[0].line(), 7);
+    }
+
+    #[test]
+    fn repeated_key_starts_a_new_record() {
+        let capture = Capture::parse(PACKS);
+        let keys: Vec<_> = capture.records.iter().filter_map(Record::key).collect();
+        assert_eq!(
+            keys,
+            [
+                ("PACK", "0 0 1 2"),
+                ("PACK", "0 0 1 3"),
+                ("PACK", "0 0 1 4")
+            ]
+        );
+        assert_eq!(capture.records[2].first("PTYP"), Some("DCM"));
+    }
+
+    #[test]
+    fn trailing_empty_req_is_not_a_record() {
+        let capture = Capture::parse(PACKS);
+        assert_eq!(capture.records.len(), 3);
+        assert!(capture.records.iter().all(|r| r.get("REQ").is_none()));
+    }
+
+    #[test]
+    fn wide_typ_padding() {
+        let capture = Capture::parse(
+            "  # ovly mbs\n    REQ   que\n    TYP    mbs\n    MBS    all\n    MBS    1\n    NAME   TEST\n",
+        );
+        assert_eq!(capture.header.first("TYP"), Some("mbs"));
+        assert_eq!(capture.header.first("MBS"), Some("all"));
+        assert_eq!(capture.records.len(), 1);
+        assert_eq!(capture.records[0].key(), Some(("MBS", "1")));
+        assert_eq!(capture.records[0].first("NAME"), Some("TEST"));
+    }
+}
@@ -0,0 +1,102 @@
+//! Scripts of DMO `NEW` requests that would recreate the captured configuration on a blank or
+//! replacement switch.
+//!
+//! Each overlay gets its own script, and the overlays (and the tables within them) are in an order
+//! where most things are created before anything that refers to them: packs before the lines and
+//! trunks on them, trunk groups before their trunks, routes before the translations that lead to
+//! them.  Hunt groups come after the directory numbers that are their members.
+//!
+//! That order can't be complete, because translations and the lines and trunk groups they lead to
+//! refer to each other: a DN or TG record names its prefix translator
+//! ([`crate::routing::PREFIX_TRANSLATOR`], e.g. `PRFX`), which is only created by the `trns`
+//! script at the end.  Where the switch won't accept a prefix translator that doesn't exist yet,
+//! answer that prompt empty when running the `dn` and `tg` scripts, then set it with a second pass
+//! of `CHG` requests in those overlays once `trns` is in.
+
+use std::path::Path;
+
+use crate::{parser::Capture, script::Script};
+
+/// Every overlay that can be rebuilt, in the order they are entered, with the captures of each in
+/// the order their entities are created.  `dn` and `tg` come before the `trns` that their prefix
+/// translators are in; see the module docs for the second pass that needs.
+pub const ORDER: &[(&str, &[&str])] = &[
+    (
+        "cpk",
+        &[
+            "CPK/PACK.txt",
+            "CPK/LPK.txt",
+            "CPK/SLC.txt",
+            "CPK/SLPK.txt",
+            "CPK/DCM.txt",
+            "CPK/IDTL.txt",
+        ],
+    ),
+    (
+        "net",
+        &[
+            "NET/D1PK.txt",
+            "NET/DS1L.txt",
+            "NET/DSI.txt",
+            "NET/IFPK.txt",
+            "NET/DSLK.txt",
+            "NET/IDT.txt",
+            "NET/EDCH.txt",
+        ],
+    ),
+    ("tg", &["TG/TG.txt", "TG/LTG.txt"]),
+    ("trk", &["TRK/TRK.txt", "TRK/DTRK.txt", "TRK/LTRK.txt"]),
+    ("rout", &["ROUT/BRTE.txt", "ROUT/ROUT.txt", "ROUT/DEST.txt"]),
+    ("dn", &["DN/DN.txt", "DN/STN.txt"]),
+    ("hunt", &["HUNT/DNH.txt", "HUNT/EBS.txt"]),
+    (
+        "trns",
+        &[
+            "TRNS/active/DNS.txt",
+            "TRNS/active/SCRN.txt",
+            "TRNS/active/EBSP.txt",
+            "TRNS/active/ADDR.txt",
+            "TRNS/active/PRFX.txt",
+        ],
+    ),
+];
+
+/// The answer to `TYP` for the entities in a capture, e.g. `pack` for `CPK/PACK.txt`.
+pub fn typ(filename: &str) -> String {
+    filename
+        .rsplit('/')
+        .next()
+        .and_then(|f| f.strip_suffix(".txt"))
+        .unwrap_or(filename)
+        .to_lowercase()
+}
+
+/// The script for each overlay that has any captures in `dir`, in the order they must be run.
+pub fn scripts(dir: impl AsRef<Path>) -> anyhow::Result<Vec<(&'static str, Script)>> {
+    let mut scripts = vec![];
+    for &(ovly, captures) in ORDER {
+        let mut script = Script::default();
+        for &file in captures {
+            let Some(capture) = Capture::load(dir.as_ref(), file)? else {
+                continue;
+            };
+            script.comment(format!("{}: {} record(s)", file, capture.records.len()));
+            for record in &capture.records {
+                let (prompt, value) = record.key().unwrap_or_default();
+                script.comment(format!("{}:{}  {}  {}", file, record.line(), prompt, value));
+                script.request_record("new", &typ(file), record);
+            }
+        }
+        if script.items.is_empty() {
+            continue;
+        }
+
+        let mut full = Script::default();
+        full.comment(format!("recreate the {} overlay", ovly.to_uppercase()));
+        full.enter_overlay(ovly);
+        full.extend(script);
+        full.leave_overlay();
+        scripts.push((ovly, full));
+    }
+    Ok(scripts)
+}
@@ -0,0 +1,323 @@
+//! Destinations, routes and basic routes from the `ROUT` overlay (`ROUT/DEST.txt`,
+//! `ROUT/ROUT.txt` and `ROUT/BRTE.txt`), which turn the result of a translation into an ordered
+//! list of trunk groups to try.
+
+use std::{fmt, path::Path};
+
+use serde::Serialize;
+
+use crate::{
+    dn::DirectoryNumbers,
+    parser::{Capture, Record},
+    translations::{Outcome, Table, Trace, Translations},
+    trunk::{self, ResolvedGroup, Trunks},
+};
+
+// how many destinations/routes are followed before giving up on a loop
+const MAX_DEPTH: usize = 16;
+
+/// The prompt on a line or trunk group that names its prefix translator, i.e. where translation of
+/// digits dialed from it starts.
+pub const PREFIX_TRANSLATOR: &[&str] = &["PRFX", "PTRN", "PFXT"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Kind {
+    Dest,
+    Rout,
+    Brte,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 3] = [Kind::Dest, Kind::Rout, Kind::Brte];
+
+    pub fn prompt(self) -> &'static str {
+        match self {
+            Kind::Dest => "DEST",
+            Kind::Rout => "ROUT",
+            Kind::Brte => "BRTE",
+        }
+    }
+
+    pub fn capture(self) -> String {
+        format!("ROUT/{}.txt", self.prompt())
+    }
+
+    fn from_prompt(prompt: &str) -> Option<Self> {
+        Kind::ALL.into_iter().find(|k| k.prompt() == prompt)
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.prompt())
+    }
+}
+
+/// Something a destination or route refers to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum Target {
+    Entry(Kind, String),
+    TrunkGroup(String),
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Entry(kind, number) => write!(f, "{} {}", kind, number),
+            Target::TrunkGroup(number) => write!(f, "TG {}", number),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RouteEntry {
+    pub kind: Kind,
+    pub number: String,
+    /// Everything this entry refers to, in the order it is tried.
+    pub targets: Vec<Target>,
+    pub line: usize,
+    pub record: Record,
+}
+
+impl RouteEntry {
+    fn from_record(kind: Kind, record: &Record) -> Option<Self> {
+        let number = record.first(kind.prompt())?.to_owned();
+        let mut targets = vec![];
+        for field in record.fields().iter().skip(1) {
+            for value in &field.values {
+                targets.push(match field.prompt.as_str() {
+                    "TG" => Target::TrunkGroup(value.clone()),
+                    prompt => match Kind::from_prompt(prompt) {
+                        Some(kind) => Target::Entry(kind, value.clone()),
+                        None => continue,
+                    },
+                });
+            }
+        }
+
+        Some(Self {
+            kind,
+            number,
+            targets,
+            line: record.line(),
+            record: record.clone(),
+        })
+    }
+}
+
+/// How a destination or route was expanded into trunk groups.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Expansion<'a> {
+    /// Every destination and route that was visited, in order.
+    pub entries: Vec<&'a RouteEntry>,
+    /// The trunk groups that would be tried, in order.
+    pub trunk_groups: Vec<String>,
+    /// Destinations and routes that were referred to but aren't in the captures.
+    pub missing: Vec<Target>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Routing {
+    pub entries: Vec<RouteEntry>,
+}
+
+impl Routing {
+    /// Read every destination and route from the captures in `dir`.  Captures that don't exist are
+    /// skipped.
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut entries = vec![];
+        for kind in Kind::ALL {
+            if let Some(capture) = Capture::load(dir.as_ref(), &kind.capture())? {
+                entries.extend(
+                    capture
+                        .records
+                        .iter()
+                        .filter_map(|r| RouteEntry::from_record(kind, r)),
+                );
+            }
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn get(&self, kind: Kind, number: &str) -> Option<&RouteEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.kind == kind && e.number == number)
+    }
+
+    /// Follow a destination or route down to the trunk groups it ends up using.
+    pub fn expand(&self, kind: Kind, number: &str) -> Expansion<'_> {
+        let mut expansion = Expansion::default();
+        self.expand_into(&Target::Entry(kind, number.to_owned()), 0, &mut expansion);
+        expansion
+    }
+
+    fn expand_into<'a>(&'a self, target: &Target, depth: usize, expansion: &mut Expansion<'a>) {
+        match target {
+            Target::TrunkGroup(number) => {
+                if !expansion.trunk_groups.contains(number) {
+                    expansion.trunk_groups.push(number.clone());
+                }
+            }
+            Target::Entry(kind, number) => {
+                let Some(entry) = self.get(*kind, number) else {
+                    expansion.missing.push(target.clone());
+                    return;
+                };
+                if depth >= MAX_DEPTH || expansion.entries.iter().any(|e| std::ptr::eq(*e, entry)) {
+                    return;
+                }
+                expansion.entries.push(entry);
+                for target in &entry.targets {
+                    self.expand_into(target, depth + 1, expansion);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expansion<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            let targets: Vec<String> = entry.targets.iter().map(|t| t.to_string()).collect();
+            writeln!(
+                f,
+                "{:16}  ({}:{})  -> {}",
+                format!("{} {}", entry.kind, entry.number),
+                entry.kind.capture(),
+                entry.line,
+                targets.join(", ")
+            )?;
+        }
+        for target in &self.missing {
+            writeln!(f, "{:16}  (not in the captures)", target.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a simulated call comes from.
+#[derive(Clone, Debug, Serialize)]
+pub enum Origin {
+    Dn(String),
+    TrunkGroup(String),
+    /// Skip looking up a line or trunk group and start in this prefix translator.
+    PrefixTranslator(String),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Dn(dn) => write!(f, "DN {}", dn),
+            Origin::TrunkGroup(tg) => write!(f, "TG {}", tg),
+            Origin::PrefixTranslator(prfx) => write!(f, "PRFX {}", prfx),
+        }
+    }
+}
+
+/// Everything that happens to a dialed number: which prefix translator it starts in, how it is
+/// translated, and which trunk groups the resulting route would try.
+#[derive(Debug, Serialize)]
+pub struct Simulation<'a> {
+    pub origin: Origin,
+    pub prefix_translator: String,
+    pub translation: Trace<'a>,
+    pub routing: Option<Expansion<'a>>,
+    pub trunk_groups: Vec<ResolvedGroup<'a>>,
+}
+
+impl<'a> Simulation<'a> {
+    pub fn run(
+        origin: Origin,
+        digits: &str,
+        numbers: &DirectoryNumbers,
+        trunks: &'a Trunks,
+        translations: &'a Translations,
+        routing: &'a Routing,
+    ) -> anyhow::Result<Self> {
+        let prefix_translator = match &origin {
+            Origin::Dn(dn) => {
+                let dn = numbers
+                    .find(dn)
+                    .ok_or_else(|| anyhow::anyhow!("DN {} is not in the captures", dn))?;
+                dn.record.first_any(PREFIX_TRANSLATOR).ok_or_else(|| {
+                    anyhow::anyhow!("DN {} does not name a prefix translator", dn.dn)
+                })?
+            }
+            Origin::TrunkGroup(number) => {
+                let group = trunks
+                    .group(trunk::Kind::Tg, number)
+                    .ok_or_else(|| anyhow::anyhow!("TG {} is not in the captures", number))?;
+                group.record.first_any(PREFIX_TRANSLATOR).ok_or_else(|| {
+                    anyhow::anyhow!("TG {} does not name a prefix translator", number)
+                })?
+            }
+            Origin::PrefixTranslator(prfx) => prfx,
+        }
+        .to_owned();
+
+        let translation = translations.translate(Table::Prfx, &prefix_translator, digits);
+        let routing = match &translation.outcome {
+            Outcome::Route(route) => Some(routing.expand(Kind::Rout, route)),
+            Outcome::Destination(dest) => Some(routing.expand(Kind::Dest, dest)),
+            _ => None,
+        };
+        let trunk_groups = routing
+            .iter()
+            .flat_map(|r| &r.trunk_groups)
+            .filter_map(|number| {
+                trunks
+                    .group(trunk::Kind::Tg, number)
+                    .map(|group| ResolvedGroup {
+                        group,
+                        trunks: trunks.members(trunk::Kind::Tg, number),
+                    })
+            })
+            .collect();
+
+        Ok(Self {
+            origin,
+            prefix_translator,
+            translation,
+            routing,
+            trunk_groups,
+        })
+    }
+}
+
+impl fmt::Display for Simulation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "from {}, starting in PRFX {}",
+            self.origin, self.prefix_translator
+        )?;
+        writeln!(f)?;
+        write!(f, "{}", self.translation)?;
+
+        let Some(routing) = &self.routing else {
+            return Ok(());
+        };
+        writeln!(f)?;
+        write!(f, "{}", routing)?;
+
+        writeln!(f)?;
+        if routing.trunk_groups.is_empty() {
+            writeln!(f, "no trunk groups would be tried")?;
+        }
+        for (i, number) in routing.trunk_groups.iter().enumerate() {
+            match self.trunk_groups.iter().find(|g| &g.group.number == number) {
+                Some(resolved) => writeln!(
+                    f,
+                    "{}. TG {}  {}  {} member trunk(s)",
+                    i + 1,
+                    number,
+                    resolved.group.name.as_deref().unwrap_or(""),
+                    resolved.trunks.len()
+                )?,
+                None => writeln!(f, "{}. TG {}  (not in the captures)", i + 1, number)?,
+            }
+        }
+        Ok(())
+    }
+}
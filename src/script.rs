@@ -0,0 +1,293 @@
+//! Scripts of DMO input: the same (bytes to send, prompt to wait for) steps that a fetcher's dialog
+//! is made of, written out so that they can be reviewed before anything is sent to the switch.
+//!
+//! The text format is one step per line, quoted like `list` prints a fetcher's dialog, with `#`
+//! starting a comment line:
+//!
+//! ```text
+//! # DN/DN.txt:7  DN  5551234
+//! send "new\n"  expect "TYP"
+//! send "dn\n"  expect "DN"
+//! ```
+//!
+//! Unlike a fetcher's dialog, a step expects a prompt by name.  The DMS-10 pads the same prompt
+//! differently from one overlay to the next (e.g. `    TYP    ` in TG), so the padding is not part
+//! of what is expected.
+
+use std::{fmt, path::Path};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::parser::Record;
+
+/// The `  # ` prompt that the DMS-10 prints when it's not in any overlay.
+pub const HASH: &str = "  # ";
+
+/// How the DMS-10 pads a prompt when asking for its value, e.g. `    REQ   `.
+pub fn dmo_prompt(prompt: &str) -> String {
+    format!("    {:4}  ", prompt)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Step {
+    pub send: String,
+    /// The name of the prompt to wait for afterwards, e.g. `REQ` or `#`, without its padding.
+    pub expect: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum Item {
+    Comment(String),
+    Step(Step),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Script {
+    pub items: Vec<Item>,
+}
+
+impl Script {
+    pub fn comment(&mut self, comment: impl Into<String>) {
+        self.items.push(Item::Comment(comment.into()));
+    }
+
+    pub fn step(&mut self, send: impl Into<String>, expect: impl Into<String>) {
+        self.items.push(Item::Step(Step {
+            send: send.into(),
+            expect: expect.into(),
+        }));
+    }
+
+    pub fn steps(&self) -> impl Iterator<Item = &Step> {
+        self.items.iter().filter_map(|item| match item {
+            Item::Step(step) => Some(step),
+            Item::Comment(_) => None,
+        })
+    }
+
+    /// Every overlay that the script enters, in the order it first enters them.
+    pub fn overlays(&self) -> Vec<String> {
+        let mut overlays: Vec<String> = vec![];
+        for step in self.steps() {
+            if let Some(ovly) = step.send.trim().strip_prefix("ovly ") {
+                let ovly = ovly.trim().to_lowercase();
+                if !overlays.contains(&ovly) {
+                    overlays.push(ovly);
+                }
+            }
+        }
+        overlays
+    }
+
+    pub fn extend(&mut self, other: Script) {
+        self.items.extend(other.items);
+    }
+
+    /// Leave whatever overlay the DMS-10 is in, and enter `ovly` at its `REQ` prompt.
+    pub fn enter_overlay(&mut self, ovly: &str) {
+        self.step("****\n", HASH.trim());
+        self.step(format!("ovly {}\n", ovly), "REQ");
+    }
+
+    /// Leave the current overlay.
+    pub fn leave_overlay(&mut self) {
+        self.step("****\n", HASH.trim());
+    }
+
+    /// Answer `request` (e.g. `new`) for an entity of type `typ`, then every prompt in `fields` in
+    /// order.  A prompt with more than one value is given the rest at its continuation prompt and
+    /// ended with an empty line.  Starts and ends at the `REQ` prompt.
+    ///
+    /// The DMS-10 is assumed to ask for prompts in the order that `QUE` prints them, which is worth
+    /// checking when reviewing the script.
+    pub fn request(&mut self, request: &str, typ: &str, fields: &[(&str, &[String])]) {
+        // every answer with the prompt it's given at, so that each step can expect the next one
+        let mut answers: Vec<(&str, String)> =
+            vec![("REQ", request.to_owned()), ("TYP", typ.to_owned())];
+        for (prompt, values) in fields {
+            let mut values = values.iter();
+            answers.push((prompt, values.next().cloned().unwrap_or_default()));
+            let rest: Vec<&String> = values.collect();
+            if !rest.is_empty() {
+                // the continuation prompt is nothing but padding
+                for value in rest {
+                    answers.push(("", value.clone()));
+                }
+                answers.push(("", String::new()));
+            }
+        }
+
+        for (i, (_, answer)) in answers.iter().enumerate() {
+            let expect = answers.get(i + 1).map_or("REQ", |(prompt, _)| prompt);
+            self.step(format!("{}\n", answer), expect);
+        }
+    }
+
+    /// Answer `request` with every prompt and value in `record`.
+    pub fn request_record(&mut self, request: &str, typ: &str, record: &Record) {
+        let fields: Vec<(&str, &[String])> = record
+            .fields()
+            .iter()
+            .map(|f| (f.prompt.as_str(), f.values.as_slice()))
+            .collect();
+        self.request(request, typ, &fields);
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut script = Script::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix('#') {
+                script.comment(comment.trim());
+                continue;
+            }
+            let step = parse_step(line).with_context(|| format!("line {}", number + 1))?;
+            script.items.push(Item::Step(step));
+        }
+        Ok(script)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        Script::parse(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in &self.items {
+            match item {
+                Item::Comment(comment) => writeln!(f, "# {}", comment)?,
+                Item::Step(step) => writeln!(f, "{}", step)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // quote everything, since the trailing whitespace in the prompts is significant
+        write!(
+            f,
+            "send \"{}\"  expect \"{}\"",
+            self.send.as_bytes().escape_ascii(),
+            self.expect.as_bytes().escape_ascii()
+        )
+    }
+}
+
+fn parse_step(line: &str) -> anyhow::Result<Step> {
+    let rest = line
+        .strip_prefix("send ")
+        .context("expected a line starting with `send`")?;
+    let (send, rest) = parse_quoted(rest.trim_start())?;
+    let rest = rest
+        .trim_start()
+        .strip_prefix("expect ")
+        .context("expected `expect` after the sent text")?;
+    let (expect, rest) = parse_quoted(rest.trim_start())?;
+    if !rest.trim().is_empty() {
+        anyhow::bail!("unexpected text after the expected prompt: {}", rest.trim());
+    }
+    Ok(Step { send, expect })
+}
+
+/// Read one string quoted the way `escape_ascii` quotes it, returning it and whatever follows.
+fn parse_quoted(s: &str) -> anyhow::Result<(String, &str)> {
+    let mut chars = s
+        .strip_prefix('"')
+        .context("expected a quoted string")?
+        .char_indices();
+    let mut bytes = vec![];
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                let value = String::from_utf8(bytes).context("quoted string is not UTF-8")?;
+                return Ok((value, &s[i + 2..]));
+            }
+            '\\' => match chars.next().map(|(_, c)| c) {
+                Some('n') => bytes.push(b'\n'),
+                Some('r') => bytes.push(b'\r'),
+                Some('t') => bytes.push(b'\t'),
+                Some('x') => {
+                    let hex: String = (0..2)
+                        .filter_map(|_| chars.next().map(|(_, c)| c))
+                        .collect();
+                    bytes.push(
+                        u8::from_str_radix(&hex, 16)
+                            .with_context(|| format!("bad escape \\x{}", hex))?,
+                    );
+                }
+                Some(c @ ('\\' | '"' | '\'')) => bytes.push(c as u8),
+                Some(c) => anyhow::bail!("unknown escape \\{}", c),
+                None => break,
+            },
+            c => {
+                let mut buffer = [0; 4];
+                bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            }
+        }
+    }
+    anyhow::bail!("unterminated quoted string")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_round_trips_through_parse() {
+        let mut script = Script::default();
+        script.comment("DN/DN.txt:7  DN  5551234");
+        script.enter_overlay("dn");
+        script.step("say \"hi\"\\\t\r\n", "    TYP   ");
+        script.step("\x07\u{7f}\n", "");
+        script.leave_overlay();
+
+        let text = script.to_string();
+        assert!(text.contains(r#"send "say \"hi\"\\\t\r\n"  expect "    TYP   ""#));
+        assert_eq!(Script::parse(&text).unwrap(), script);
+    }
+
+    #[test]
+    fn parse_rejects_malformed_lines() {
+        assert!(Script::parse("send \"que\\n\"").is_err());
+        assert!(Script::parse("send \"que  expect \"REQ\"").is_err());
+        assert!(Script::parse("send \"\\q\"  expect \"REQ\"").is_err());
+        assert!(Script::parse("send \"que\"  expect \"REQ\" extra").is_err());
+    }
+
+    #[test]
+    fn request_expects_each_next_prompt_by_name() {
+        let mut script = Script::default();
+        let options = ["ABC".to_owned(), "DEF".to_owned()];
+        script.request(
+            "new",
+            "dn",
+            &[("DN", &["5551234".to_owned()]), ("OPTS", &options)],
+        );
+
+        let steps: Vec<(&str, &str)> = script
+            .steps()
+            .map(|s| (s.send.as_str(), s.expect.as_str()))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                ("new\n", "TYP"),
+                ("dn\n", "DN"),
+                ("5551234\n", "OPTS"),
+                ("ABC\n", ""),
+                ("DEF\n", ""),
+                ("\n", "REQ"),
+            ]
+        );
+    }
+}
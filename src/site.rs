@@ -0,0 +1,636 @@
+//! A static website for browsing a directory of captures: a page per overlay with every record
+//! in it, a page per directory number, trunk group, hunt group and route, and links between them
+//! wherever one refers to another.  It needs nothing but a browser to read, so it can be published
+//! next to the captures or opened straight from disk.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::{
+    diff::CaptureSet,
+    dn::{DirectoryNumber, DirectoryNumbers},
+    hunt::{self, HuntGroup, HuntGroups},
+    inventory::Inventory,
+    parser::Record,
+    routing::{self, RouteEntry, Routing, Target},
+    translations::{Next, Set, Translations},
+    trunk::{self, TrunkGroup, Trunks},
+};
+
+// Prompts whose values are directory numbers, trunk groups or equipment locations, and so are
+// linked to the page about them.
+const DN_PROMPTS: &[&str] = &["DN", "MEMB", "MBR", "OVFL", "OVF", "OFDN"];
+const TG_PROMPTS: &[&str] = &["TG", "LTG"];
+const EQUIPMENT_PROMPTS: &[&str] = &["LEN", "LTID", "TEN"];
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; vertical-align: top; }
+th { background: #f4f4f4; }
+.source { color: #777; font-size: smaller; }
+nav a { margin-right: 1em; }";
+
+const SEARCH_SCRIPT: &str = "const input = document.getElementById('query');
+const results = document.getElementById('results');
+input.addEventListener('input', () => {
+  const query = input.value.trim().toLowerCase();
+  results.innerHTML = '';
+  if (!query) return;
+  for (const entry of SEARCH_INDEX) {
+    if (!(entry.title + ' ' + entry.text).toLowerCase().includes(query)) continue;
+    const item = document.createElement('li');
+    const link = document.createElement('a');
+    link.href = entry.url;
+    link.textContent = entry.title;
+    item.appendChild(link);
+    results.appendChild(item);
+    if (results.children.length >= 200) break;
+  }
+});";
+
+/// Wrap `body` in a complete HTML page.
+pub fn document(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>\n{}\n</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        body
+    )
+}
+
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct SearchEntry {
+    title: String,
+    url: String,
+    text: String,
+}
+
+/// Everything that the site is generated from.
+pub struct Site {
+    captures: CaptureSet,
+    numbers: DirectoryNumbers,
+    // the page of each number by its digits, so that most links don't need a `find`
+    dn_urls: HashMap<String, String>,
+    trunks: Trunks,
+    hunt: HuntGroups,
+    routing: Routing,
+    translations: Translations,
+    inventory: Inventory,
+}
+
+impl Site {
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        let numbers = DirectoryNumbers::load(dir)?;
+        let mut dn_urls = HashMap::new();
+        for dn in numbers.iter() {
+            // the first capture wins, like `DirectoryNumbers::find`
+            dn_urls.entry(dn.digits()).or_insert_with(|| dn_url(dn));
+        }
+        Ok(Self {
+            captures: CaptureSet::Dir(dir.to_owned()),
+            numbers,
+            dn_urls,
+            trunks: Trunks::load(dir)?,
+            hunt: HuntGroups::load(dir)?,
+            routing: Routing::load(dir)?,
+            translations: Translations::load(dir, Set::Active)?,
+            inventory: Inventory::load(dir)?,
+        })
+    }
+
+    /// Write every page into `out`, creating it if need be, and return how many were written.
+    pub fn write(&self, out: impl AsRef<Path>) -> anyhow::Result<usize> {
+        let out = out.as_ref();
+        let mut pages: Vec<(PathBuf, String)> = vec![];
+        let mut index: Vec<SearchEntry> = vec![];
+
+        let overlays = self.overlays()?;
+        for (overlay, files) in &overlays {
+            pages.push((
+                overlay_url(overlay).into(),
+                self.overlay_page(overlay, files, &mut index)?,
+            ));
+        }
+
+        let hunt_groups = self.hunt_groups_by_dn();
+        for dn in self.numbers.iter() {
+            index.push(SearchEntry {
+                title: format!("DN {}", dn.dn),
+                url: dn_url(dn),
+                text: [dn.len.as_deref(), dn.class.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .chain(dn.options.iter().map(String::as_str))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            });
+            let groups = hunt_groups
+                .get(&(dn.source, dn.line))
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            pages.push((dn_url(dn).into(), self.dn_page(dn, groups)));
+        }
+        for group in &self.trunks.groups {
+            index.push(SearchEntry {
+                title: format!("{} {}", group.kind, group.number),
+                url: tg_url(group),
+                text: group.name.clone().unwrap_or_default(),
+            });
+            pages.push((tg_url(group).into(), self.tg_page(group)));
+        }
+        for group in &self.hunt.groups {
+            let url = hunt_url(group.kind, &group.number);
+            index.push(SearchEntry {
+                title: format!("{} {}", group.kind.prompt(), group.number),
+                url: url.clone(),
+                text: group.members.join(" "),
+            });
+            pages.push((url.into(), self.hunt_page(group)));
+        }
+        for entry in &self.routing.entries {
+            let url = route_url(entry.kind, &entry.number);
+            index.push(SearchEntry {
+                title: format!("{} {}", entry.kind, entry.number),
+                url: url.clone(),
+                text: String::new(),
+            });
+            pages.push((url.into(), self.route_page(entry)));
+        }
+
+        pages.push(("dn/index.html".into(), self.dn_index()));
+        pages.push(("tg/index.html".into(), self.tg_index()));
+        pages.push(("hunt/index.html".into(), self.hunt_index()));
+        pages.push(("route/index.html".into(), self.route_index()));
+        pages.push(("inventory.html".into(), self.inventory_page()));
+        pages.push(("search.html".into(), self.search_page()));
+        pages.push(("index.html".into(), self.index_page(&overlays)));
+
+        for (path, html) in &pages {
+            let path = out.join(path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("creating {}", parent.display()))?;
+            }
+            std::fs::write(&path, html).with_context(|| format!("writing {}", path.display()))?;
+        }
+
+        let script = format!("const SEARCH_INDEX = {};\n", serde_json::to_string(&index)?);
+        let path = out.join("search-index.js");
+        std::fs::write(&path, script).with_context(|| format!("writing {}", path.display()))?;
+
+        // a record that the DMS-10 printed twice is still only one page
+        Ok(pages
+            .iter()
+            .map(|(path, _)| path)
+            .collect::<BTreeSet<_>>()
+            .len())
+    }
+
+    /// The hunt groups that each number is a member (or the overflow) of, by the number's source
+    /// and line.
+    fn hunt_groups_by_dn(&self) -> HashMap<(&'static str, usize), Vec<&HuntGroup>> {
+        let mut groups: HashMap<(&'static str, usize), Vec<&HuntGroup>> = HashMap::new();
+        for group in &self.hunt.groups {
+            let mut members: Vec<&DirectoryNumber> = group
+                .members
+                .iter()
+                .chain(&group.overflow)
+                .filter_map(|m| self.numbers.find(m))
+                .collect();
+            members.dedup_by_key(|dn| (dn.source, dn.line));
+            for dn in members {
+                let groups = groups.entry((dn.source, dn.line)).or_default();
+                if !groups.iter().any(|g| std::ptr::eq(*g, group)) {
+                    groups.push(group);
+                }
+            }
+        }
+        groups
+    }
+
+    /// Every capture, grouped by the overlay it came from (the first part of its path).
+    fn overlays(&self) -> anyhow::Result<BTreeMap<String, Vec<String>>> {
+        let mut overlays: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for file in self.captures.list()? {
+            let overlay = file.split('/').next().unwrap_or(&file).to_owned();
+            overlays.entry(overlay).or_default().push(file);
+        }
+        Ok(overlays)
+    }
+
+    fn page(&self, title: &str, root: &str, body: &str) -> String {
+        let nav = format!(
+            "<nav><a href=\"{root}index.html\">Overlays</a><a href=\"{root}dn/index.html\">DNs</a>\
+             <a href=\"{root}tg/index.html\">Trunk groups</a><a href=\"{root}hunt/index.html\">Hunt groups</a>\
+             <a href=\"{root}route/index.html\">Routes</a><a href=\"{root}inventory.html\">Inventory</a>\
+             <a href=\"{root}search.html\">Search</a></nav>\n<h1>{}</h1>\n",
+            escape(title)
+        );
+        document(title, &(nav + body))
+    }
+
+    /// Where a value printed after `prompt` should link to, if there's a page about it.
+    fn link(&self, prompt: &str, value: &str) -> Option<String> {
+        if DN_PROMPTS.contains(&prompt) {
+            let digits: String = value.chars().filter(char::is_ascii_digit).collect();
+            return match self.dn_urls.get(&digits) {
+                Some(url) => Some(url.clone()),
+                None => self.numbers.find(value).map(dn_url),
+            };
+        }
+        if TG_PROMPTS.contains(&prompt) {
+            return self
+                .trunks
+                .group(trunk::Kind::from_prompt(prompt), value)
+                .map(tg_url);
+        }
+        if EQUIPMENT_PROMPTS.contains(&prompt) {
+            return self
+                .inventory
+                .pack_for(value)
+                .map(|p| format!("inventory.html#{}", pack_anchor(&p.location)));
+        }
+        for kind in hunt::Kind::ALL {
+            if prompt == kind.prompt() {
+                return self
+                    .hunt
+                    .get(kind, value)
+                    .map(|g| hunt_url(g.kind, &g.number));
+            }
+        }
+        for kind in routing::Kind::ALL {
+            if prompt == kind.prompt() {
+                return self
+                    .routing
+                    .get(kind, value)
+                    .map(|e| route_url(e.kind, &e.number));
+            }
+        }
+        None
+    }
+
+    fn record_table(&self, record: &Record, root: &str) -> String {
+        let mut html = String::from("<table>\n");
+        for field in record.fields() {
+            let values: Vec<String> = field
+                .values
+                .iter()
+                .map(|value| match self.link(&field.prompt, value) {
+                    Some(url) => format!("<a href=\"{}{}\">{}</a>", root, url, escape(value)),
+                    None => escape(value),
+                })
+                .collect();
+            let _ = writeln!(
+                html,
+                "<tr><th>{}</th><td>{}</td></tr>",
+                escape(&field.prompt),
+                values.join("<br>")
+            );
+        }
+        html.push_str("</table>\n");
+        html
+    }
+
+    /// A link to where `record` is on its overlay's page.
+    fn source(&self, file: &str, record: &Record, root: &str) -> String {
+        format!(
+            "<p class=\"source\"><a href=\"{}{}#{}\">{}:{}</a></p>\n",
+            root,
+            overlay_url(file.split('/').next().unwrap_or(file)),
+            record_anchor(file, record),
+            escape(file),
+            record.line()
+        )
+    }
+
+    fn overlay_page(
+        &self,
+        overlay: &str,
+        files: &[String],
+        index: &mut Vec<SearchEntry>,
+    ) -> anyhow::Result<String> {
+        let mut body = String::new();
+        for file in files {
+            let capture = self
+                .captures
+                .read(file)
+                .with_context(|| format!("reading {}", file))?;
+            let _ = writeln!(body, "<h2 id=\"{}\">{}</h2>", slug(file), escape(file));
+            if capture.records.is_empty() {
+                body.push_str("<p>(no records)</p>\n");
+            }
+            for record in &capture.records {
+                let (prompt, value) = record.key().unwrap_or_default();
+                let anchor = record_anchor(file, record);
+                index.push(SearchEntry {
+                    title: format!("{}: {}  {}", file, prompt, value),
+                    url: format!("{}#{}", overlay_url(overlay), anchor),
+                    text: record
+                        .fields()
+                        .iter()
+                        .flat_map(|f| &f.values)
+                        .map(String::as_str)
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                });
+                let _ = writeln!(
+                    body,
+                    "<div id=\"{}\">\n<p class=\"source\">{}:{}</p>",
+                    anchor,
+                    escape(file),
+                    record.line()
+                );
+                body.push_str(&self.record_table(record, "../"));
+                body.push_str("</div>\n");
+            }
+        }
+        Ok(self.page(&format!("Overlay {}", overlay), "../", &body))
+    }
+
+    fn dn_page(&self, dn: &DirectoryNumber, groups: &[&HuntGroup]) -> String {
+        let mut body = self.source(dn.source, &dn.record, "../");
+        body.push_str(&self.record_table(&dn.record, "../"));
+
+        if !groups.is_empty() {
+            body.push_str("<h2>Hunt groups</h2>\n<ul>\n");
+            for group in groups {
+                let _ = writeln!(
+                    body,
+                    "<li><a href=\"../{}\">{} {}</a></li>",
+                    hunt_url(group.kind, &group.number),
+                    group.kind.prompt(),
+                    escape(&group.number)
+                );
+            }
+            body.push_str("</ul>\n");
+        }
+        self.page(&format!("DN {}", dn.dn), "../", &body)
+    }
+
+    fn tg_page(&self, group: &TrunkGroup) -> String {
+        let mut body = self.source(group.source, &group.record, "../");
+        body.push_str(&self.record_table(&group.record, "../"));
+
+        body.push_str("<h2>Member trunks</h2>\n");
+        let members = self.trunks.members(group.kind, &group.number);
+        if members.is_empty() {
+            body.push_str("<p>(none)</p>\n");
+        } else {
+            body.push_str("<table>\n<tr><th>Member</th><th>Equipment</th><th>Source</th></tr>\n");
+            for trunk in members {
+                let _ = writeln!(
+                    body,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    escape(trunk.member.as_deref().unwrap_or("")),
+                    escape(&trunk.equipment),
+                    self.source(trunk.source, &trunk.record, "../")
+                );
+            }
+            body.push_str("</table>\n");
+        }
+
+        // routes only ever name TGs
+        if group.kind == trunk::Kind::Tg {
+            let target = Target::TrunkGroup(group.number.clone());
+            body.push_str(&self.used_by(|e| e.targets.contains(&target)));
+        }
+        self.page(&format!("{} {}", group.kind, group.number), "../", &body)
+    }
+
+    fn hunt_page(&self, group: &HuntGroup) -> String {
+        let mut body = self.source(group.kind.capture(), &group.record, "../");
+        body.push_str(&self.record_table(&group.record, "../"));
+        self.page(
+            &format!("{} {}", group.kind.prompt(), group.number),
+            "../",
+            &body,
+        )
+    }
+
+    fn route_page(&self, entry: &RouteEntry) -> String {
+        let mut body = self.source(&entry.kind.capture(), &entry.record, "../");
+        body.push_str(&self.record_table(&entry.record, "../"));
+
+        let target = Target::Entry(entry.kind, entry.number.clone());
+        body.push_str(&self.used_by(|e| e.targets.contains(&target)));
+
+        let translations: Vec<_> = self
+            .translations
+            .entries
+            .iter()
+            .filter(|t| match (&t.next, entry.kind) {
+                (Some(Next::Route(n)), routing::Kind::Rout) => n == &entry.number,
+                (Some(Next::Destination(n)), routing::Kind::Dest) => n == &entry.number,
+                _ => false,
+            })
+            .collect();
+        if !translations.is_empty() {
+            body.push_str("<h2>Translated to by</h2>\n<ul>\n");
+            for translation in translations {
+                let _ = writeln!(
+                    body,
+                    "<li>{}{}</li>",
+                    escape(&translation.key()),
+                    self.source(&translation.source, &translation.record, "../")
+                );
+            }
+            body.push_str("</ul>\n");
+        }
+        self.page(&format!("{} {}", entry.kind, entry.number), "../", &body)
+    }
+
+    /// A list of the routes and destinations that `uses` is true of.
+    fn used_by(&self, uses: impl Fn(&RouteEntry) -> bool) -> String {
+        let entries: Vec<&RouteEntry> = self.routing.entries.iter().filter(|e| uses(e)).collect();
+        if entries.is_empty() {
+            return String::new();
+        }
+        let mut html = String::from("<h2>Used by</h2>\n<ul>\n");
+        for entry in entries {
+            let _ = writeln!(
+                html,
+                "<li><a href=\"../{}\">{} {}</a></li>",
+                route_url(entry.kind, &entry.number),
+                entry.kind,
+                escape(&entry.number)
+            );
+        }
+        html.push_str("</ul>\n");
+        html
+    }
+
+    fn dn_index(&self) -> String {
+        let links: Vec<(String, String)> = self
+            .numbers
+            .iter()
+            .map(|dn| {
+                (
+                    dn_url(dn),
+                    format!("{}  {}", dn.dn, dn.len.as_deref().unwrap_or("")),
+                )
+            })
+            .collect();
+        self.page("Directory numbers", "../", &link_list(&links, "../"))
+    }
+
+    fn tg_index(&self) -> String {
+        let links: Vec<(String, String)> = self
+            .trunks
+            .groups
+            .iter()
+            .map(|g| {
+                (
+                    tg_url(g),
+                    format!(
+                        "{} {}  {}",
+                        g.kind,
+                        g.number,
+                        g.name.as_deref().unwrap_or("")
+                    ),
+                )
+            })
+            .collect();
+        self.page("Trunk groups", "../", &link_list(&links, "../"))
+    }
+
+    fn hunt_index(&self) -> String {
+        let links: Vec<(String, String)> = self
+            .hunt
+            .groups
+            .iter()
+            .map(|g| {
+                (
+                    hunt_url(g.kind, &g.number),
+                    format!("{} {}", g.kind.prompt(), g.number),
+                )
+            })
+            .collect();
+        self.page("Hunt groups", "../", &link_list(&links, "../"))
+    }
+
+    fn route_index(&self) -> String {
+        let links: Vec<(String, String)> = self
+            .routing
+            .entries
+            .iter()
+            .map(|e| {
+                (
+                    route_url(e.kind, &e.number),
+                    format!("{} {}", e.kind, e.number),
+                )
+            })
+            .collect();
+        self.page("Routes", "../", &link_list(&links, "../"))
+    }
+
+    fn inventory_page(&self) -> String {
+        self.page("Hardware inventory", "", &self.inventory.html_body())
+    }
+
+    fn search_page(&self) -> String {
+        let body = format!(
+            "<input id=\"query\" type=\"search\" placeholder=\"DN, TG, CLLI, equipment...\" autofocus>\n\
+             <ul id=\"results\"></ul>\n<script src=\"search-index.js\"></script>\n<script>\n{}\n</script>\n",
+            SEARCH_SCRIPT
+        );
+        self.page("Search", "", &body)
+    }
+
+    fn index_page(&self, overlays: &BTreeMap<String, Vec<String>>) -> String {
+        let links: Vec<(String, String)> = overlays
+            .iter()
+            .map(|(overlay, files)| {
+                (
+                    overlay_url(overlay),
+                    format!("{}  ({} capture(s))", overlay, files.len()),
+                )
+            })
+            .collect();
+        self.page("DMS-10 configuration", "", &link_list(&links, ""))
+    }
+}
+
+fn link_list(links: &[(String, String)], root: &str) -> String {
+    if links.is_empty() {
+        return "<p>(none captured)</p>\n".to_owned();
+    }
+    let mut html = String::from("<ul>\n");
+    for (url, text) in links {
+        let _ = writeln!(
+            html,
+            "<li><a href=\"{}{}\">{}</a></li>",
+            root,
+            url,
+            escape(text)
+        );
+    }
+    html.push_str("</ul>\n");
+    html
+}
+
+/// Something safe to use in a filename or anchor, e.g. `0 0 1 2` → `0-0-1-2`.
+fn slug(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+fn overlay_url(overlay: &str) -> String {
+    format!("overlay/{}.html", slug(overlay))
+}
+
+/// A number in both `DN/DN.txt` and `DN/STN.txt` gets a page for each, e.g. `dn/stn-5551234.html`.
+fn dn_url(dn: &DirectoryNumber) -> String {
+    let typ = Path::new(dn.source)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    format!("dn/{}-{}.html", slug(&typ), slug(&dn.digits()))
+}
+
+fn tg_url(group: &TrunkGroup) -> String {
+    format!(
+        "tg/{}-{}.html",
+        group.kind.to_string().to_lowercase(),
+        slug(&group.number)
+    )
+}
+
+fn hunt_url(kind: hunt::Kind, number: &str) -> String {
+    format!(
+        "hunt/{}-{}.html",
+        kind.prompt().to_lowercase(),
+        slug(number)
+    )
+}
+
+fn route_url(kind: routing::Kind, number: &str) -> String {
+    format!(
+        "route/{}-{}.html",
+        kind.prompt().to_lowercase(),
+        slug(number)
+    )
+}
+
+fn record_anchor(file: &str, record: &Record) -> String {
+    format!("{}-L{}", slug(file), record.line())
+}
+
+pub fn pack_anchor(location: &str) -> String {
+    format!("pack-{}", slug(location))
+}
@@ -0,0 +1,91 @@
+use std::process::ExitCode;
+
+use log::warn;
+
+/// Every fetcher that was attempted completed successfully.
+pub const EXIT_SUCCESS: u8 = 0;
+/// We logged in to the DMS-10, but at least one fetcher failed.
+pub const EXIT_PARTIAL_FAILURE: u8 = 2;
+/// We never made it to the `  # ` prompt, so nothing was fetched at all, or the connection was lost
+/// partway through the run.
+pub const EXIT_CONNECTION_FAILURE: u8 = 3;
+
+pub enum Outcome {
+    Ok,
+    Failed(anyhow::Error),
+    Skipped(String),
+}
+
+/// A record of what happened to each fetcher, in the order they were run, so that a long run can
+/// be summarized at the end instead of relying on someone scrolling back through the logs.
+#[derive(Default)]
+pub struct Summary {
+    results: Vec<(String, Outcome)>,
+    disconnected: bool,
+}
+
+impl Summary {
+    pub fn ok(&mut self, filename: &str) {
+        self.results.push((filename.to_owned(), Outcome::Ok));
+    }
+
+    pub fn failed(&mut self, filename: &str, error: anyhow::Error) {
+        warn!("{} failed: {:#}", filename, error);
+        self.results
+            .push((filename.to_owned(), Outcome::Failed(error)));
+    }
+
+    pub fn skipped(&mut self, filename: &str, reason: impl Into<String>) {
+        self.results
+            .push((filename.to_owned(), Outcome::Skipped(reason.into())));
+    }
+
+    /// Record that the connection was lost, which takes precedence over any individual failure.
+    pub fn disconnected(&mut self) {
+        self.disconnected = true;
+    }
+
+    pub fn has_failures(&self) -> bool {
+        self.results
+            .iter()
+            .any(|(_, outcome)| matches!(outcome, Outcome::Failed(_)))
+    }
+
+    /// Print a table of every fetcher's result to stdout.
+    pub fn print(&self) {
+        let width = self
+            .results
+            .iter()
+            .map(|(filename, _)| filename.len())
+            .max()
+            .unwrap_or(0)
+            .max("FILE".len());
+
+        println!("{:width$}  {:7}  REASON", "FILE", "RESULT");
+        for (filename, outcome) in &self.results {
+            match outcome {
+                Outcome::Ok => println!("{:width$}  ok", filename),
+                Outcome::Failed(e) => println!("{:width$}  failed   {:#}", filename, e),
+                Outcome::Skipped(reason) => println!("{:width$}  skipped  {}", filename, reason),
+            }
+        }
+
+        let count = |f: fn(&Outcome) -> bool| self.results.iter().filter(|(_, o)| f(o)).count();
+        println!(
+            "{} ok, {} failed, {} skipped",
+            count(|o| matches!(o, Outcome::Ok)),
+            count(|o| matches!(o, Outcome::Failed(_))),
+            count(|o| matches!(o, Outcome::Skipped(_))),
+        );
+    }
+
+    pub fn exit_code(&self) -> ExitCode {
+        if self.disconnected {
+            ExitCode::from(EXIT_CONNECTION_FAILURE)
+        } else if self.has_failures() {
+            ExitCode::from(EXIT_PARTIAL_FAILURE)
+        } else {
+            ExitCode::from(EXIT_SUCCESS)
+        }
+    }
+}
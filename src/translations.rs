@@ -0,0 +1,404 @@
+//! Digit translations from the `TRNS` overlay, as captured by the `trns_active` and
+//! `trns_inactive` fetchers (`TRNS/active/*.txt` and `TRNS/inactive/*.txt`).
+//!
+//! Each table is made of entries keyed by a translator number and a string of leading digits:
+//!
+//! ```text
+//!     PRFX  3 1
+//!     ADDR  2
+//! ```
+//!
+//! A dialed number is translated by finding the entry in the current translator with the longest
+//! matching digits, then following whatever that entry points to: another translator (`ADDR`,
+//! `SCRN`, `EBSP`, `DNS`), or a final result (`ROUT`, `DEST`, `TRMT`).  Prefix translators consume
+//! the digits they match; every other table passes the whole remaining number along.  An entry in
+//! `DNS` that doesn't point anywhere else means the number is a local directory number.
+
+use std::{fmt, path::Path};
+
+use serde::Serialize;
+
+use crate::parser::{Capture, Record};
+
+// the longest chain of translators that is followed before giving up on a loop
+const MAX_STEPS: usize = 32;
+
+// prompts that hold the digits of an entry, for tables that don't put them in the key
+pub(crate) const DIGITS: &[&str] = &["DIGS", "DGTS", "DIGT"];
+const ROUTE: &str = "ROUT";
+const DESTINATION: &str = "DEST";
+const TREATMENT: &str = "TRMT";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, clap::ValueEnum)]
+pub enum Table {
+    Dns,
+    Addr,
+    Ebsp,
+    Prfx,
+    Scrn,
+}
+
+impl Table {
+    pub const ALL: [Table; 5] = [
+        Table::Dns,
+        Table::Addr,
+        Table::Ebsp,
+        Table::Prfx,
+        Table::Scrn,
+    ];
+
+    /// The `TYP` of this table, which is also its key prompt and the name of its capture.
+    pub fn prompt(self) -> &'static str {
+        match self {
+            Table::Dns => "DNS",
+            Table::Addr => "ADDR",
+            Table::Ebsp => "EBSP",
+            Table::Prfx => "PRFX",
+            Table::Scrn => "SCRN",
+        }
+    }
+
+    pub fn from_prompt(prompt: &str) -> Option<Self> {
+        Table::ALL
+            .into_iter()
+            .find(|t| t.prompt().eq_ignore_ascii_case(prompt))
+    }
+
+    /// Whether this table is fetched as an inactive set too.  DNS is only ever fetched with `QUE`,
+    /// so there's nothing to compare it against.
+    pub fn has_inactive_set(self) -> bool {
+        self != Table::Dns
+    }
+
+    /// The table that a capture of either set holds, e.g. `Addr` for `TRNS/inactive/ADDR.txt`.
+    pub fn from_capture(filename: &str) -> Option<Self> {
+        let (_set, file) = filename.strip_prefix("TRNS/")?.split_once('/')?;
+        Table::from_prompt(file.strip_suffix(".txt")?)
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.prompt())
+    }
+}
+
+/// Which set of translations to read: the ones in service (`QUE`) or the ones staged to replace
+/// them (`QUEI`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, clap::ValueEnum)]
+pub enum Set {
+    Active,
+    Inactive,
+}
+
+impl Set {
+    /// The capture that `table` in this set is read from, e.g. `TRNS/inactive/ADDR.txt`.
+    pub fn capture(self, table: Table) -> String {
+        let set = match self {
+            Set::Active => "active",
+            Set::Inactive => "inactive",
+        };
+        format!("TRNS/{}/{}.txt", set, table.prompt())
+    }
+}
+
+/// Where an entry sends the call next.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum Next {
+    Translator(Table, String),
+    Route(String),
+    Destination(String),
+    Treatment(String),
+}
+
+impl fmt::Display for Next {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Next::Translator(table, translator) => write!(f, "{} {}", table, translator),
+            Next::Route(route) => write!(f, "ROUT {}", route),
+            Next::Destination(dest) => write!(f, "DEST {}", dest),
+            Next::Treatment(treatment) => write!(f, "TRMT {}", treatment),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Entry {
+    pub table: Table,
+    pub translator: String,
+    pub digits: String,
+    pub next: Option<Next>,
+    /// The capture this entry was read from.
+    pub source: String,
+    pub line: usize,
+    pub record: Record,
+}
+
+impl Entry {
+    fn from_record(table: Table, record: &Record, source: &str) -> Option<Self> {
+        let key = record.first(table.prompt())?;
+        let mut tokens = key.split_whitespace();
+        let translator = tokens.next()?.to_owned();
+        let digits = match record.first_any(DIGITS) {
+            Some(digits) => dialed(digits),
+            None => dialed(&tokens.collect::<String>()),
+        };
+
+        // the first field after the key that points somewhere else
+        let next = record.fields().iter().skip(1).find_map(|field| {
+            let value = field.values.first()?.clone();
+            match field.prompt.as_str() {
+                ROUTE => Some(Next::Route(value)),
+                DESTINATION => Some(Next::Destination(value)),
+                TREATMENT => Some(Next::Treatment(value)),
+                prompt => Table::from_prompt(prompt).map(|t| Next::Translator(t, value)),
+            }
+        });
+
+        Some(Self {
+            table,
+            translator,
+            digits,
+            next,
+            source: source.to_owned(),
+            line: record.line(),
+            record: record.clone(),
+        })
+    }
+
+    /// The key of the entry that `record` (from `table`'s capture) is, without the rest of the
+    /// entry; see `key`.
+    pub fn key_of(table: Table, record: &Record) -> Option<String> {
+        Self::from_record(table, record, "").map(|entry| entry.key())
+    }
+
+    /// The prompts and answers that pick out `record` in an `OUT` or `CHG` of `table`: the
+    /// translator at the table's own prompt, then the digits at whichever digits prompt the record
+    /// was printed with.  Digits printed on the key's line (e.g. `PRFX  1 6`) are answered with the
+    /// translator, as printed, and an entry with no digits is picked out by its translator alone.
+    pub fn selection(table: Table, record: &Record) -> Option<Vec<(&str, String)>> {
+        let key = record
+            .first(table.prompt())
+            .filter(|key| !key.trim().is_empty())?;
+        let mut fields = vec![(table.prompt(), key.to_owned())];
+        let digits = record
+            .fields()
+            .iter()
+            .find(|f| DIGITS.contains(&f.prompt.as_str()))
+            .and_then(|f| Some((f.prompt.as_str(), f.values.first()?)));
+        if let Some((prompt, digits)) = digits {
+            if !digits.trim().is_empty() {
+                fields.push((prompt, digits.clone()));
+            }
+        }
+        Some(fields)
+    }
+
+    /// The key that identifies this entry within its table, e.g. `PRFX 3 1`.
+    pub fn key(&self) -> String {
+        if self.digits.is_empty() {
+            format!("{} {}", self.table, self.translator)
+        } else {
+            format!("{} {} {}", self.table, self.translator, self.digits)
+        }
+    }
+}
+
+/// One translator that a dialed number passed through.
+#[derive(Clone, Debug, Serialize)]
+pub struct Step<'a> {
+    pub entry: &'a Entry,
+    /// The digits that were presented to this translator.
+    pub digits: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum Outcome {
+    Route(String),
+    Destination(String),
+    Treatment(String),
+    /// The number terminates on a directory number in this office.
+    Local,
+    /// The last translator that was reached has no entry matching the digits.
+    NoMatch {
+        table: Table,
+        translator: String,
+        digits: String,
+    },
+    /// An entry was found, but it doesn't say where to go next.
+    Unresolved,
+    Loop,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Route(route) => write!(f, "ROUT {}", route),
+            Outcome::Destination(dest) => write!(f, "DEST {}", dest),
+            Outcome::Treatment(treatment) => write!(f, "treatment {}", treatment),
+            Outcome::Local => write!(f, "local directory number"),
+            Outcome::NoMatch {
+                table,
+                translator,
+                digits,
+            } => write!(
+                f,
+                "no entry in {} {} matches \"{}\"",
+                table, translator, digits
+            ),
+            Outcome::Unresolved => write!(f, "the last entry does not lead anywhere"),
+            Outcome::Loop => write!(
+                f,
+                "gave up after {} translators; is there a loop?",
+                MAX_STEPS
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Trace<'a> {
+    pub steps: Vec<Step<'a>>,
+    pub outcome: Outcome,
+}
+
+impl fmt::Display for Trace<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            write!(
+                f,
+                "{:16}  \"{}\"  ({}:{})",
+                step.entry.key(),
+                step.digits,
+                step.entry.source,
+                step.entry.line
+            )?;
+            match &step.entry.next {
+                Some(next) => writeln!(f, "  -> {}", next)?,
+                None => writeln!(f)?,
+            }
+        }
+        writeln!(f, "result: {}", self.outcome)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Translations {
+    pub set: Set,
+    pub entries: Vec<Entry>,
+}
+
+impl Translations {
+    /// Read every table in `set` from the captures in `dir`.  Tables that weren't captured are
+    /// treated as empty.
+    pub fn load(dir: impl AsRef<Path>, set: Set) -> anyhow::Result<Self> {
+        let mut entries = vec![];
+        for table in Table::ALL {
+            let source = set.capture(table);
+            if let Some(capture) = Capture::load(dir.as_ref(), &source)? {
+                entries.extend(
+                    capture
+                        .records
+                        .iter()
+                        .filter_map(|r| Entry::from_record(table, r, &source)),
+                );
+            }
+        }
+        Ok(Self { set, entries })
+    }
+
+    /// All entries in one table, across all translators.
+    pub fn table(&self, table: Table) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(move |e| e.table == table)
+    }
+
+    /// The entry in `table`/`translator` with the longest digits that `digits` starts with.
+    pub fn lookup(&self, table: Table, translator: &str, digits: &str) -> Option<&Entry> {
+        self.table(table)
+            .filter(|e| e.translator == translator && digits.starts_with(&e.digits))
+            .max_by_key(|e| e.digits.len())
+    }
+
+    /// Follow `digits` through the translators, starting at `translator` in `table`, until it
+    /// reaches a route, destination or treatment (or can't go any further).
+    pub fn translate(&self, table: Table, translator: &str, digits: &str) -> Trace<'_> {
+        let mut steps = vec![];
+        let mut table = table;
+        let mut translator = translator.to_owned();
+        let mut digits = dialed(digits);
+
+        for _ in 0..MAX_STEPS {
+            let Some(entry) = self.lookup(table, &translator, &digits) else {
+                return Trace {
+                    steps,
+                    outcome: Outcome::NoMatch {
+                        table,
+                        translator,
+                        digits,
+                    },
+                };
+            };
+            steps.push(Step {
+                entry,
+                digits: digits.clone(),
+            });
+
+            if table == Table::Prfx {
+                digits = digits[entry.digits.len()..].to_owned();
+            }
+
+            let outcome = match &entry.next {
+                Some(Next::Translator(next_table, next_translator)) => {
+                    table = *next_table;
+                    translator = next_translator.clone();
+                    continue;
+                }
+                Some(Next::Route(route)) => Outcome::Route(route.clone()),
+                Some(Next::Destination(dest)) => Outcome::Destination(dest.clone()),
+                Some(Next::Treatment(treatment)) => Outcome::Treatment(treatment.clone()),
+                None if table == Table::Dns => Outcome::Local,
+                None => Outcome::Unresolved,
+            };
+            return Trace { steps, outcome };
+        }
+
+        Trace {
+            steps,
+            outcome: Outcome::Loop,
+        }
+    }
+}
+
+/// Just what can be dialed (digits, `*` and `#`), so that `*69`, `* 69` and `555-1234` are matched
+/// the same way in entries and in the digits being translated.
+fn dialed(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_ascii_digit() || matches!(c, '*' | '#'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_codes_are_not_ordinary_digits() {
+        let capture = Capture::parse("    PRFX  1 6\n    ROUT  3\n\n    PRFX  1 *6\n    ROUT  9\n");
+        let translations = Translations {
+            set: Set::Active,
+            entries: capture
+                .records
+                .iter()
+                .filter_map(|r| Entry::from_record(Table::Prfx, r, "TRNS/active/PRFX.txt"))
+                .collect(),
+        };
+
+        let route = |digits| match translations.translate(Table::Prfx, "1", digits).outcome {
+            Outcome::Route(route) => route,
+            outcome => panic!("{} did not route: {:?}", digits, outcome),
+        };
+        assert_eq!(route("*69"), "9");
+        assert_eq!(route("* 69"), "9");
+        assert_eq!(route("6-9"), "3");
+    }
+}
@@ -0,0 +1,281 @@
+//! Trunk groups (`TG/TG.txt`, `TG/LTG.txt`) and their member trunks (`TRK/TRK.txt`,
+//! `TRK/DTRK.txt`, `TRK/LTRK.txt`).
+
+use std::{fmt, path::Path};
+
+use serde::Serialize;
+
+use crate::parser::{Capture, Record};
+
+pub const GROUP_CAPTURES: &[&str] = &["TG/TG.txt", "TG/LTG.txt"];
+pub const TRUNK_CAPTURES: &[&str] = &["TRK/TRK.txt", "TRK/DTRK.txt", "TRK/LTRK.txt"];
+
+// The prompts for each attribute, in order of preference where the overlays spell the same thing
+// differently.
+const GROUP_NUMBER: &[&str] = &["NUM", "TG", "LTG"];
+const GROUP_NAME: &[&str] = &["CLLI", "NAME"];
+const DIRECTION: &[&str] = &["DIR", "TGTP"];
+const EQUIPMENT: &[&str] = &["TEN", "LEN", "TRK", "DTRK", "LTRK"];
+const MEMBER: &[&str] = &["MEMB", "MBR", "MEM"];
+const TRUNK_GROUP: &[&str] = &["TG", "NUM", "LTG"];
+
+/// Which kind of group a number refers to.  TGs and LTGs are numbered independently, so `TG 1` and
+/// `LTG 1` are different groups.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Tg,
+    Ltg,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 2] = [Kind::Tg, Kind::Ltg];
+
+    /// The kind of group that a group or trunk capture is about: `TG/LTG.txt` and `TRK/LTRK.txt`
+    /// are LTGs, everything else is a TG.
+    fn of(source: &str) -> Self {
+        if source.ends_with("/LTG.txt") || source.ends_with("/LTRK.txt") {
+            Kind::Ltg
+        } else {
+            Kind::Tg
+        }
+    }
+
+    /// The capture that groups of this kind are read from.
+    pub fn capture(self) -> &'static str {
+        match self {
+            Kind::Tg => GROUP_CAPTURES[0],
+            Kind::Ltg => GROUP_CAPTURES[1],
+        }
+    }
+
+    /// The kind of group a value printed after `prompt` (e.g. `LTG`) refers to.
+    pub fn from_prompt(prompt: &str) -> Self {
+        if prompt == "LTG" {
+            Kind::Ltg
+        } else {
+            Kind::Tg
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Tg => "TG",
+            Kind::Ltg => "LTG",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TrunkGroup {
+    pub kind: Kind,
+    pub number: String,
+    pub name: Option<String>,
+    pub direction: Option<String>,
+    /// The capture this group was read from, e.g. `TG/LTG.txt`.
+    pub source: &'static str,
+    pub line: usize,
+    pub record: Record,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Trunk {
+    /// Where the trunk circuit is physically located.
+    pub equipment: String,
+    /// The kind of group this trunk can be a member of.
+    pub kind: Kind,
+    /// The number of the group this trunk is a member of, if it is assigned to one.
+    pub group: Option<String>,
+    /// The member number within the group.
+    pub member: Option<String>,
+    pub source: &'static str,
+    pub line: usize,
+    pub record: Record,
+}
+
+impl TrunkGroup {
+    fn from_record(record: &Record, source: &'static str) -> Option<Self> {
+        Some(Self {
+            kind: Kind::of(source),
+            number: record.first_any(GROUP_NUMBER)?.to_owned(),
+            name: record.first_any(GROUP_NAME).map(str::to_owned),
+            direction: record.first_any(DIRECTION).map(str::to_owned),
+            source,
+            line: record.line(),
+            record: record.clone(),
+        })
+    }
+}
+
+impl Trunk {
+    fn from_record(record: &Record, source: &'static str) -> Option<Self> {
+        Some(Self {
+            equipment: record.first_any(EQUIPMENT)?.to_owned(),
+            kind: Kind::of(source),
+            group: record.first_any(TRUNK_GROUP).map(str::to_owned),
+            member: record.first_any(MEMBER).map(str::to_owned),
+            source,
+            line: record.line(),
+            record: record.clone(),
+        })
+    }
+
+    fn member_number(&self) -> u32 {
+        self.member
+            .as_deref()
+            .and_then(|m| m.parse().ok())
+            .unwrap_or(u32::MAX)
+    }
+}
+
+/// A trunk group together with every trunk that claims to be a member of it.
+#[derive(Debug, Serialize)]
+pub struct ResolvedGroup<'a> {
+    pub group: &'a TrunkGroup,
+    pub trunks: Vec<&'a Trunk>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Trunks {
+    pub groups: Vec<TrunkGroup>,
+    pub trunks: Vec<Trunk>,
+}
+
+impl Trunks {
+    /// Read every trunk group and trunk from the captures in `dir`.  Captures that don't exist are
+    /// skipped.
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut trunks = Self::default();
+        for &source in GROUP_CAPTURES {
+            if let Some(capture) = Capture::load(dir.as_ref(), source)? {
+                trunks.groups.extend(
+                    capture
+                        .records
+                        .iter()
+                        .filter_map(|r| TrunkGroup::from_record(r, source)),
+                );
+            }
+        }
+        for &source in TRUNK_CAPTURES {
+            if let Some(capture) = Capture::load(dir.as_ref(), source)? {
+                trunks.trunks.extend(
+                    capture
+                        .records
+                        .iter()
+                        .filter_map(|r| Trunk::from_record(r, source)),
+                );
+            }
+        }
+        Ok(trunks)
+    }
+
+    pub fn group(&self, kind: Kind, number: &str) -> Option<&TrunkGroup> {
+        self.groups
+            .iter()
+            .find(|g| g.kind == kind && g.number == number)
+    }
+
+    /// The trunks in group `number` of `kind`, in member order.  `TRK/LTRK.txt` has the members
+    /// of LTGs, and the other trunk captures have the members of TGs.
+    pub fn members(&self, kind: Kind, number: &str) -> Vec<&Trunk> {
+        let mut members: Vec<&Trunk> = self
+            .trunks
+            .iter()
+            .filter(|t| t.kind == kind && t.group.as_deref() == Some(number))
+            .collect();
+        members.sort_by_key(|t| t.member_number());
+        members
+    }
+
+    /// Every group with its member trunks.
+    pub fn resolve(&self) -> Vec<ResolvedGroup<'_>> {
+        self.groups
+            .iter()
+            .map(|group| ResolvedGroup {
+                group,
+                trunks: self.members(group.kind, &group.number),
+            })
+            .collect()
+    }
+
+    /// Trunks whose group doesn't appear in any of the group captures, or that have no group.
+    pub fn unassigned(&self) -> impl Iterator<Item = &Trunk> {
+        self.trunks.iter().filter(|t| match &t.group {
+            Some(number) => self.group(t.kind, number).is_none(),
+            None => true,
+        })
+    }
+}
+
+impl fmt::Display for ResolvedGroup<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.group.kind, self.group.number)?;
+        if let Some(name) = &self.group.name {
+            write!(f, "  {}", name)?;
+        }
+        if let Some(direction) = &self.group.direction {
+            write!(f, "  {}", direction)?;
+        }
+        writeln!(f, "  ({}:{})", self.group.source, self.group.line)?;
+
+        if self.trunks.is_empty() {
+            writeln!(f, "    (no member trunks)")?;
+        }
+        for trunk in &self.trunks {
+            writeln!(
+                f,
+                "    MEMB {:>4}  {:12}  ({}:{})",
+                trunk.member.as_deref().unwrap_or("?"),
+                trunk.equipment,
+                trunk.source,
+                trunk.line
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tg_and_ltg_with_the_same_number_are_different_groups() {
+        let mut trunks = Trunks::default();
+        for (source, text) in [
+            ("TG/TG.txt", "    NUM   1\n    CLLI  TOLL\n"),
+            ("TG/LTG.txt", "    LTG   1\n    NAME  LOCAL\n"),
+        ] {
+            let capture = Capture::parse(text);
+            trunks
+                .groups
+                .extend(TrunkGroup::from_record(&capture.records[0], source));
+        }
+        for (source, text) in [
+            ("TRK/TRK.txt", "    TRK   0 1 2 3\n    TG    1\n"),
+            ("TRK/LTRK.txt", "    LTRK  0 1 2 4\n    LTG   1\n"),
+        ] {
+            let capture = Capture::parse(text);
+            trunks
+                .trunks
+                .extend(Trunk::from_record(&capture.records[0], source));
+        }
+
+        assert_eq!(
+            trunks.group(Kind::Ltg, "1").unwrap().name.as_deref(),
+            Some("LOCAL")
+        );
+        let equipment = |kind| -> Vec<&str> {
+            trunks
+                .members(kind, "1")
+                .iter()
+                .map(|t| t.equipment.as_str())
+                .collect()
+        };
+        assert_eq!(equipment(Kind::Tg), ["0 1 2 3"]);
+        assert_eq!(equipment(Kind::Ltg), ["0 1 2 4"]);
+        assert_eq!(trunks.unassigned().count(), 0);
+    }
+}